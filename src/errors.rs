@@ -0,0 +1,111 @@
+// Copyright 2016 Gomez Guillaume
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::error;
+use std::fmt::{Display, Formatter, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScopeError {
+    /// An `OutScope` event at index `at` closes a scope which was never opened.
+    Unbalanced { at: usize },
+    /// `count` scopes were still open at the end of the stream.
+    UnclosedScopes { count: usize },
+    /// The `InScope` event at index `at` opens a scope for a file module (`mod m;`).
+    FileModuleScope { at: usize },
+    /// The fragment at index `fragment` doesn't have valid scopes on its own.
+    InvalidFragment { fragment: usize },
+    /// The item at index `item` has children but can't have a scope, like a file module.
+    UnscopedChildren { item: usize },
+}
+
+impl Display for ScopeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            ScopeError::Unbalanced { at } => {
+                write!(f, "unbalanced scope: event {} closes a scope which isn't open", at)
+            }
+            ScopeError::UnclosedScopes { count } => {
+                write!(f, "{} scope(s) not closed at the end of the events", count)
+            }
+            ScopeError::FileModuleScope { at } => {
+                write!(f, "event {} opens a scope for a file module", at)
+            }
+            ScopeError::InvalidFragment { fragment } => {
+                write!(f, "fragment {} doesn't have valid scopes", fragment)
+            }
+            ScopeError::UnscopedChildren { item } => {
+                write!(f, "item {} has children but can't have a scope", item)
+            }
+        }
+    }
+}
+
+impl error::Error for ScopeError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameError {
+    Empty,
+    StartsWithDigit(String),
+    InvalidCharacter { name: String, c: char },
+    Keyword(String),
+}
+
+impl Display for NameError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            NameError::Empty => write!(f, "empty name"),
+            NameError::StartsWithDigit(ref name) => {
+                write!(f, "name \"{}\" starts with a digit", name)
+            }
+            NameError::InvalidCharacter { ref name, c } => {
+                write!(f, "name \"{}\" contains invalid character {:?}", name, c)
+            }
+            NameError::Keyword(ref name) => write!(f, "name \"{}\" is a keyword", name),
+        }
+    }
+}
+
+impl error::Error for NameError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The line the error occurred on, starting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_error_messages() {
+        assert_eq!(ScopeError::Unbalanced { at: 3 }.to_string(),
+                   "unbalanced scope: event 3 closes a scope which isn't open");
+        assert_eq!(ScopeError::UnclosedScopes { count: 2 }.to_string(),
+                   "2 scope(s) not closed at the end of the events");
+        assert_eq!(ScopeError::FileModuleScope { at: 5 }.to_string(),
+                   "event 5 opens a scope for a file module");
+        assert_eq!(ScopeError::InvalidFragment { fragment: 1 }.to_string(),
+                   "fragment 1 doesn't have valid scopes");
+    }
+}
@@ -0,0 +1,1383 @@
+// Copyright 2016 Gomez Guillaume
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io::{BufRead, ErrorKind};
+use std::iter::Peekable;
+use std::mem;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+#[cfg(feature = "serde")]
+use serde_json;
+
+use consts::INDENT;
+use errors::{ParseError, ScopeError};
+#[cfg(feature = "serde")]
+use types::serialize_fields;
+use types::{set_stream_attribute, EventType, Span, Type, TypeStruct};
+
+pub fn validate_scopes(events: &[EventType]) -> Result<(), ScopeError> {
+    let mut depth = 0usize;
+    for (pos, event) in events.iter().enumerate() {
+        match *event {
+            EventType::InScope => {
+                if pos > 0 && matches!(events[pos - 1],
+                                       EventType::Type(ref t) if t.ty == Type::Mod && !t.inline) {
+                    return Err(ScopeError::FileModuleScope { at: pos });
+                }
+                depth += 1;
+            }
+            EventType::OutScope => {
+                if depth == 0 {
+                    return Err(ScopeError::Unbalanced { at: pos });
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        Err(ScopeError::UnclosedScopes { count: depth })
+    } else {
+        Ok(())
+    }
+}
+
+// Returns the scope depth each event is written at.
+fn line_depths(events: &[EventType]) -> Vec<usize> {
+    let mut depth = 0usize;
+    events.iter().map(|event| {
+        match *event {
+            EventType::InScope => {
+                depth += 1;
+                depth - 1
+            }
+            EventType::OutScope => {
+                depth = depth.saturating_sub(1);
+                depth
+            }
+            _ => depth,
+        }
+    }).collect()
+}
+
+// Every fragment is checked with `validate_scopes` before being appended, so the result is
+// valid as long as they all are.
+pub fn concat_events(fragments: Vec<Vec<EventType>>) -> Result<Vec<EventType>, ScopeError> {
+    let mut events = Vec::with_capacity(fragments.iter().map(Vec::len).sum());
+    for (fragment, mut fragment_events) in fragments.into_iter().enumerate() {
+        if validate_scopes(&fragment_events).is_err() {
+            return Err(ScopeError::InvalidFragment { fragment });
+        }
+        events.append(&mut fragment_events);
+    }
+    Ok(events)
+}
+
+pub fn events_serialized_len(events: &[EventType]) -> usize {
+    events.iter().zip(line_depths(events)).map(|(event, depth)| match *event {
+        EventType::Blank(n) => n,
+        _ => depth * INDENT.len() + event.serialized_len() + 1,
+    }).sum()
+}
+
+// Every field of `TypeStruct`s is written, for each level of the parent chain, so
+// `read_events` gives them back as they were.
+pub fn write_events(events: &[EventType]) -> String {
+    let mut out = String::with_capacity(events_serialized_len(events));
+    for (event, depth) in events.iter().zip(line_depths(events)) {
+        match *event {
+            EventType::Blank(n) => {
+                for _ in 0..n {
+                    out.push('\n');
+                }
+            }
+            _ => {
+                out.push_str(&event.render_line(depth));
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('s') => out.push('§'),
+            Some(c) => return Err(format!("unknown escape sequence \"\\{}\"", c)),
+            None => return Err("unfinished escape sequence".to_owned()),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_type(s: &str) -> Result<TypeStruct, String> {
+    let mut parent: Option<TypeStruct> = None;
+    for segment in s.split('§') {
+        // Spans and attributes are about the level of the parent chain coming before them.
+        if segment.starts_with('@') || segment.starts_with('+') {
+            let t = match parent {
+                Some(ref mut t) => t,
+                None => return Err(format!("\"{}\" without a type", segment)),
+            };
+            if let Some(attribute) = segment.strip_prefix('+') {
+                let (key, value) = match attribute.find('=') {
+                    Some(pos) => (&attribute[..pos], Some(unescape(&attribute[pos + 1..])?)),
+                    None => (attribute, None),
+                };
+                set_stream_attribute(t, key, value)?;
+                continue;
+            }
+            let span = &segment[1..];
+            t.span = match span.find("..").map(|pos| (&span[..pos], &span[pos + 2..])) {
+                Some((start, end)) => match (start.parse(), end.parse()) {
+                    (Ok(start), Ok(end)) => Some(Span { start, end }),
+                    _ => return Err(format!("invalid span \"{}\"", span)),
+                },
+                None => return Err(format!("invalid span \"{}\"", span)),
+            };
+            continue;
+        }
+        let mut fields = segment.split('\t');
+        let keyword = fields.next().unwrap_or("");
+        let ty = match Type::all().iter().find(|t| t.as_str() == keyword) {
+            Some(&ty) => ty,
+            None => return Err(format!("unknown type \"{}\"", keyword)),
+        };
+        let name = match fields.next() {
+            Some(name) => unescape(name)?,
+            None => return Err(format!("missing name after \"{}\"", keyword)),
+        };
+        let mut t = TypeStruct::new(ty, &name);
+        for arg in fields {
+            t.args.push(unescape(arg)?);
+        }
+        t.parent = parent.map(Box::new);
+        parent = Some(t);
+    }
+    // `split` always gives at least one segment.
+    Ok(parent.unwrap())
+}
+
+fn parse_event(line: &str) -> Result<EventType, String> {
+    match line {
+        "{" => Ok(EventType::InScope),
+        "}" => Ok(EventType::OutScope),
+        _ if line.starts_with("c ") => unescape(&line[2..]).map(EventType::Comment),
+        _ if line.starts_with("r ") => unescape(&line[2..]).map(EventType::TrailingComment),
+        _ if line.starts_with("i ") => unescape(&line[2..]).map(EventType::InnerComment),
+        _ if line.starts_with("f ") => unescape(&line[2..]).map(EventType::FileComment),
+        _ if line.starts_with("p ") => unescape(&line[2..]).map(EventType::File),
+        _ if line.starts_with("t ") => parse_type(&line[2..]).map(EventType::Type),
+        _ => Err(format!("unrecognized line \"{}\"", line)),
+    }
+}
+
+// Stops at the first error unless `lossy` is set, in which case bad lines are skipped.
+fn read<R: BufRead>(reader: R, lossy: bool) -> (Vec<EventType>, Vec<ParseError>) {
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+    let mut blanks = 0;
+    for (pos, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                errors.push(ParseError { line: pos + 1, message: e.to_string() });
+                // A line which isn't valid UTF-8 is skipped like any other bad line, but the
+                // reader can't be trusted anymore after any other I/O error.
+                if lossy && e.kind() == ErrorKind::InvalidData {
+                    continue;
+                }
+                break;
+            }
+        };
+        // Only the indentation is trimmed, trailing spaces may be part of a comment.
+        let line = line.trim_start();
+        if line.trim_end().is_empty() {
+            blanks += 1;
+            continue;
+        }
+        if blanks > 0 {
+            events.push(EventType::Blank(blanks));
+            blanks = 0;
+        }
+        match parse_event(line) {
+            Ok(event) => events.push(event),
+            Err(message) => {
+                errors.push(ParseError { line: pos + 1, message });
+                if !lossy {
+                    break;
+                }
+            }
+        }
+    }
+    if blanks > 0 {
+        events.push(EventType::Blank(blanks));
+    }
+    (events, errors)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Indent {
+    /// That many spaces per level.
+    Spaces(usize),
+    Tab,
+}
+
+// Looks at the first indented line, which is at depth 1 since scopes are written on their own
+// lines. Defaults to the indentation `write_events` uses.
+//
+// `read_events` takes the scope depth from the `{` and `}` lines and skips whatever indentation
+// comes before an event, so it reads streams indented with tabs or any number of spaces alike.
+// This is for tools editing such a stream which want to keep the indentation of its producer
+// for the lines they add.
+pub fn detect_indent(input: &str) -> Indent {
+    for line in input.lines().filter(|l| !l.trim().is_empty()) {
+        if line.starts_with('\t') {
+            return Indent::Tab;
+        }
+        match line.find(|c| c != ' ') {
+            Some(0) => {}
+            Some(spaces) => return Indent::Spaces(spaces),
+            None => {}
+        }
+    }
+    Indent::Spaces(INDENT.len())
+}
+
+pub fn read_events<R: BufRead>(reader: R) -> Result<Vec<EventType>, ParseError> {
+    let (events, mut errors) = read(reader, false);
+    if errors.is_empty() {
+        Ok(events)
+    } else {
+        Err(errors.remove(0))
+    }
+}
+
+pub fn read_events_lossy<R: BufRead>(reader: R) -> (Vec<EventType>, Vec<ParseError>) {
+    read(reader, true)
+}
+
+pub fn retain_types(events: Vec<EventType>) -> Vec<EventType> {
+    events.into_iter()
+          .filter(|e| !e.is_documentation())
+          .collect()
+}
+
+// Returns, for each event, the index of the `Type` event it documents if it's a comment:
+//
+// * leading comments are attached to the next `Type` event, unless a scope change or a file
+//   comment comes in between.
+// * trailing comments are attached to the item right before them, which is the one owning
+//   the scope if they follow an `OutScope`.
+// * inner comments are attached to the item owning the enclosing scope.
+fn comment_targets(events: &[EventType]) -> Vec<Option<usize>> {
+    let mut targets = vec![None; events.len()];
+    let mut pending = Vec::new();
+    let mut scopes = Vec::new();
+    let mut previous = None;
+    for (pos, event) in events.iter().enumerate() {
+        match *event {
+            EventType::Comment(_) => pending.push(pos),
+            EventType::TrailingComment(_) => {
+                targets[pos] = previous;
+                continue;
+            }
+            EventType::InnerComment(_) => targets[pos] = scopes.last().cloned().and_then(|s| s),
+            EventType::Blank(_) => {}
+            EventType::Type(_) => {
+                for c in pending.drain(..) {
+                    targets[c] = Some(pos);
+                }
+                previous = Some(pos);
+                continue;
+            }
+            EventType::InScope => {
+                scopes.push(previous);
+                pending.clear();
+            }
+            EventType::OutScope => {
+                pending.clear();
+                previous = scopes.pop().and_then(|s| s);
+                continue;
+            }
+            EventType::FileComment(_) | EventType::File(_) => pending.clear(),
+        }
+        previous = None;
+    }
+    targets
+}
+
+pub fn extract_comments(events: &[EventType]) -> Vec<(Option<TypeStruct>, String)> {
+    let targets = comment_targets(events);
+    let mut comments = Vec::new();
+    for (pos, event) in events.iter().enumerate() {
+        match *event {
+            EventType::Comment(ref c) |
+            EventType::TrailingComment(ref c) |
+            EventType::InnerComment(ref c) => {
+                let target = targets[pos].and_then(|t| match events[t] {
+                    EventType::Type(ref t) => Some(t.clone()),
+                    _ => None,
+                });
+                comments.push((target, c.clone()));
+            }
+            EventType::FileComment(ref c) => comments.push((None, c.clone())),
+            _ => {}
+        }
+    }
+    comments
+}
+
+pub fn dedup_file_comments(mut events: Vec<EventType>) -> Vec<EventType> {
+    events.dedup_by(|a, b| matches!(*a, EventType::FileComment(_)) && a == b);
+    events
+}
+
+// A block is a run of leading comments with nothing in between, so blocks are separated by
+// blank lines or other events. A block identical to an earlier block of the same item is
+// removed, along with the blank lines separating it from the previous one. Repeated lines
+// inside of a block are kept.
+pub fn dedup_item_docs(events: Vec<EventType>) -> Vec<EventType> {
+    let targets = comment_targets(&events);
+    let mut blocks: BTreeMap<usize, Vec<Vec<usize>>> = BTreeMap::new();
+    for (pos, &target) in targets.iter().enumerate() {
+        if let (&EventType::Comment(_), Some(target)) = (&events[pos], target) {
+            let item_blocks = blocks.entry(target).or_default();
+            match item_blocks.last_mut() {
+                Some(block) if block.last() == Some(&(pos - 1)) => block.push(pos),
+                _ => item_blocks.push(vec![pos]),
+            }
+        }
+    }
+    let mut removed = vec![false; events.len()];
+    for item_blocks in blocks.values() {
+        for (i, block) in item_blocks.iter().enumerate() {
+            let repeated = item_blocks[..i].iter().any(|earlier| {
+                earlier.len() == block.len() &&
+                earlier.iter().zip(block).all(|(&a, &b)| events[a] == events[b])
+            });
+            if !repeated {
+                continue;
+            }
+            for &pos in block {
+                removed[pos] = true;
+            }
+            let previous_end = item_blocks[i - 1][item_blocks[i - 1].len() - 1];
+            for pos in previous_end + 1..block[0] {
+                if let EventType::Blank(_) = events[pos] {
+                    removed[pos] = true;
+                }
+            }
+        }
+    }
+    events.into_iter()
+          .zip(removed)
+          .filter(|&(_, removed)| !removed)
+          .map(|(event, _)| event)
+          .collect()
+}
+
+// Like `Display`, `Type::Unknown` items are shown with their raw keyword, if any.
+pub fn events_to_outline(events: &[EventType]) -> String {
+    let mut outline = String::new();
+    let mut depth = 0usize;
+    for event in events {
+        match *event {
+            EventType::InScope => depth += 1,
+            EventType::OutScope => depth = depth.saturating_sub(1),
+            EventType::Type(ref t) => {
+                outline.push_str(&"    ".repeat(depth));
+                match (t.ty, t.raw_keyword.as_ref()) {
+                    (Type::Unknown, Some(raw)) => outline.push_str(&format!("{} ", raw)),
+                    (Type::Unknown, None) => {}
+                    (ty, _) => outline.push_str(&format!("{} ", ty)),
+                }
+                outline.push_str(&format!("{}\n", t.name));
+            }
+            _ => {}
+        }
+    }
+    outline
+}
+
+// Returns every item of the stream, with its parent set to the item owning the enclosing
+// scope. Trait methods followed by a scope are marked as `has_default`.
+pub fn build_tree(events: &[EventType]) -> Vec<TypeStruct> {
+    let mut items = Vec::new();
+    let mut scopes: Vec<Option<TypeStruct>> = Vec::new();
+    let mut last = None;
+    for (pos, event) in events.iter().enumerate() {
+        match *event {
+            EventType::Type(ref t) => {
+                let mut item = t.clone();
+                item.parent = scopes.last().and_then(|s| s.clone()).map(Box::new);
+                let in_trait = matches!(item.parent, Some(ref p) if p.ty == Type::Trait);
+                if item.ty == Type::Fn && in_trait {
+                    item.has_default = matches!(events.get(pos + 1), Some(&EventType::InScope));
+                }
+                items.push(item.clone());
+                last = Some(item);
+                continue;
+            }
+            EventType::InScope => scopes.push(last.take()),
+            EventType::OutScope => {
+                scopes.pop();
+            }
+            _ => continue,
+        }
+        last = None;
+    }
+    items
+}
+
+// `items` is expected to contain every item of the tree, in the order `build_tree` returns
+// them: the children of an item are the items after it whose parent is equal to it, up to the
+// next item equal to it. This way, equal items such as two `impl Foo` blocks each keep their
+// own children. Items whose parent doesn't come before them are roots. Required trait methods
+// are written without a scope, so it's an error for them to have children, as well as for
+// file modules and the other items which can't have a scope.
+pub fn events_from_tree(items: &[TypeStruct]) -> Result<Vec<EventType>, ScopeError> {
+    fn push_item(pos: usize, items: &[TypeStruct], children: &[Vec<usize>],
+                 events: &mut Vec<EventType>) -> Result<(), ScopeError> {
+        let item = &items[pos];
+        events.push(EventType::Type(item.clone()));
+        if !item.ty.is_scoped() || (item.ty == Type::Mod && !item.inline) ||
+           item.is_required_method() {
+            return match children[pos].first() {
+                Some(_) => Err(ScopeError::UnscopedChildren { item: pos }),
+                None => Ok(()),
+            };
+        }
+        events.push(EventType::InScope);
+        for &child in &children[pos] {
+            push_item(child, items, children, events)?;
+        }
+        events.push(EventType::OutScope);
+        Ok(())
+    }
+
+    let mut children = vec![Vec::new(); items.len()];
+    let mut roots = Vec::new();
+    for (pos, item) in items.iter().enumerate() {
+        let owner = match item.parent {
+            Some(ref p) => items[..pos].iter().rposition(|i| *i == **p),
+            None => None,
+        };
+        match owner {
+            Some(owner) => children[owner].push(pos),
+            None => roots.push(pos),
+        }
+    }
+    let mut events = Vec::new();
+    for root in roots {
+        push_item(root, items, &children, &mut events)?;
+    }
+    Ok(events)
+}
+
+// Returns the index of the `OutScope` closing the `InScope` at `open`.
+fn scope_end(events: &[EventType], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (pos, event) in events.iter().enumerate().skip(open) {
+        match *event {
+            EventType::InScope => depth += 1,
+            EventType::OutScope => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Returns the range of events making up the item at `type_index`: the `Type` event itself
+// and its scope, if it has one.
+fn item_span(events: &[EventType], type_index: usize) -> (usize, usize) {
+    match events.get(type_index + 1) {
+        Some(&EventType::InScope) => {
+            let end = scope_end(events, type_index + 1).unwrap_or(events.len() - 1);
+            (type_index, end)
+        }
+        _ => (type_index, type_index),
+    }
+}
+
+// Items without a scope have no children, neither does an out of bounds `type_index`.
+pub fn count_direct_children(events: &[EventType], type_index: usize) -> usize {
+    if type_index >= events.len() {
+        return 0;
+    }
+    let (start, end) = item_span(events, type_index);
+    let mut depth = 0usize;
+    let mut count = 0;
+    for event in &events[start + 1..=end] {
+        match *event {
+            EventType::InScope => depth += 1,
+            EventType::OutScope => depth -= 1,
+            EventType::Type(_) if depth == 1 => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+enum Block {
+    // An item with its leading comments, its scope and its trailing comments.
+    Item(String, Vec<EventType>),
+    // Anything not attached to an item, which items aren't moved across.
+    Fixed(Vec<EventType>),
+}
+
+// Sorts the events up to the end of the current scope, the `OutScope` being consumed. Also
+// returns whether the scope was closed.
+fn sort_level<I: Iterator<Item = EventType>>(events: &mut Peekable<I>) -> (Vec<EventType>, bool) {
+    fn scope<I: Iterator<Item = EventType>>(events: &mut Peekable<I>) -> Vec<EventType> {
+        let (inner, closed) = sort_level(events);
+        let mut sorted = vec![EventType::InScope];
+        sorted.extend(inner);
+        if closed {
+            sorted.push(EventType::OutScope);
+        }
+        sorted
+    }
+
+    let mut blocks = Vec::new();
+    let mut pending = Vec::new();
+    let mut closed = false;
+    while let Some(event) = events.next() {
+        match event {
+            EventType::Comment(_) | EventType::Blank(_) => pending.push(event),
+            EventType::Type(t) => {
+                let name = t.impl_target().unwrap_or(&t.name).to_owned();
+                let mut block = mem::take(&mut pending);
+                block.push(EventType::Type(t));
+                if let Some(&EventType::InScope) = events.peek() {
+                    events.next();
+                    block.extend(scope(events));
+                }
+                while let Some(&EventType::TrailingComment(_)) = events.peek() {
+                    block.extend(events.next());
+                }
+                blocks.push(Block::Item(name, block));
+            }
+            EventType::InScope => {
+                pending.extend(scope(events));
+                blocks.push(Block::Fixed(mem::take(&mut pending)));
+            }
+            EventType::OutScope => {
+                closed = true;
+                break;
+            }
+            event => {
+                pending.push(event);
+                blocks.push(Block::Fixed(mem::take(&mut pending)));
+            }
+        }
+    }
+    if !pending.is_empty() {
+        blocks.push(Block::Fixed(pending));
+    }
+
+    for run in blocks.split_mut(|b| matches!(*b, Block::Fixed(_))) {
+        run.sort_by(|a, b| match (a, b) {
+            (Block::Item(a, _), Block::Item(b, _)) => a.cmp(b),
+            _ => Ordering::Equal,
+        });
+    }
+    let mut sorted = Vec::new();
+    for block in blocks {
+        match block {
+            Block::Item(_, events) | Block::Fixed(events) => sorted.extend(events),
+        }
+    }
+    (sorted, closed)
+}
+
+// Items are sorted by name inside of each scope, taking their comments and scope along. Impls
+// are sorted by the type they're for, staying after the items of the same name which came
+// before them. Anything else, such as file comments or inner comments, stays in place and
+// items aren't moved across it.
+pub fn sort_within_scopes(events: Vec<EventType>) -> Vec<EventType> {
+    let mut events = events.into_iter().peekable();
+    let mut sorted = Vec::new();
+    loop {
+        let (level, closed) = sort_level(&mut events);
+        sorted.extend(level);
+        // An unbalanced `OutScope` ends the top level early, what follows is sorted apart.
+        if !closed {
+            return sorted;
+        }
+        sorted.push(EventType::OutScope);
+    }
+}
+
+// Removes the item at `type_index` along with its scope, if it has one. The comments
+// documenting it are kept, unlike with `filter_types`. Returns `false`, leaving `events`
+// untouched, if `type_index` isn't the index of a `Type` event.
+pub fn remove_scope(events: &mut Vec<EventType>, type_index: usize) -> bool {
+    match events.get(type_index) {
+        Some(&EventType::Type(_)) => {}
+        _ => return false,
+    }
+    let (start, end) = item_span(events, type_index);
+    events.drain(start..=end);
+    true
+}
+
+// Removed items take their scope and the comments documenting them along.
+pub fn filter_types<F: Fn(&TypeStruct) -> bool>(events: Vec<EventType>,
+                                                pred: F) -> Vec<EventType> {
+    let mut removed = vec![false; events.len()];
+    let mut pos = 0;
+    while pos < events.len() {
+        match events[pos] {
+            EventType::Type(ref t) if !pred(t) => {
+                let (start, end) = item_span(&events, pos);
+                for r in &mut removed[start..=end] {
+                    *r = true;
+                }
+                pos = end + 1;
+            }
+            _ => pos += 1,
+        }
+    }
+    for (pos, target) in comment_targets(&events).into_iter().enumerate() {
+        if let Some(target) = target {
+            removed[pos] = removed[pos] || removed[target];
+        }
+    }
+    events.into_iter()
+          .zip(removed)
+          .filter(|&(_, removed)| !removed)
+          .map(|(event, _)| event)
+          .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of events, scope markers and blank lines included.
+    pub total_events: usize,
+    /// Number of `EventType::Type` events for each `Type`.
+    pub per_type: BTreeMap<Type, usize>,
+    /// Number of comments, file comments included.
+    pub comments: usize,
+    /// Deepest level of scope nesting reached.
+    pub max_depth: usize,
+    /// Whether `validate_scopes` accepts the stream.
+    pub balanced: bool,
+}
+
+pub fn events_statistics(events: &[EventType]) -> Stats {
+    let mut stats = Stats {
+        total_events: events.len(),
+        balanced: validate_scopes(events).is_ok(),
+        ..Stats::default()
+    };
+    let mut depth = 0usize;
+    for event in events {
+        match *event {
+            EventType::Type(ref t) => *stats.per_type.entry(t.ty).or_insert(0) += 1,
+            ref e if e.is_documentation() => stats.comments += 1,
+            EventType::InScope => {
+                depth += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+            }
+            EventType::OutScope => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    stats
+}
+
+// Events coming before the first `File` event are grouped under an empty path.
+pub fn group_by_file(events: Vec<EventType>) -> Vec<(String, Vec<EventType>)> {
+    let mut groups: Vec<(String, Vec<EventType>)> = Vec::new();
+    for event in events {
+        match event {
+            EventType::File(path) => groups.push((path, Vec::new())),
+            event => match groups.last_mut() {
+                Some(group) => group.1.push(event),
+                None => groups.push((String::new(), vec![event])),
+            },
+        }
+    }
+    groups
+}
+
+#[cfg(feature = "serde")]
+struct Node<'a> {
+    item: &'a TypeStruct,
+    children: Vec<Node<'a>>,
+}
+
+// Same fields as the `Serialize` implementation of `TypeStruct`, with the `children` of the
+// item in place of its `parent`.
+#[cfg(feature = "serde")]
+impl<'a> Serialize for Node<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Node", 16)?;
+        serialize_fields(self.item, &mut s)?;
+        s.serialize_field("children", &self.children)?;
+        s.end()
+    }
+}
+
+// Reads the items up to the end of the current scope. The content of scopes which don't
+// belong to an item is added to the current level.
+#[cfg(feature = "serde")]
+fn read_nodes<'a>(events: &'a [EventType], pos: &mut usize) -> Vec<Node<'a>> {
+    let mut nodes = Vec::new();
+    while *pos < events.len() {
+        *pos += 1;
+        match events[*pos - 1] {
+            EventType::Type(ref item) => {
+                let mut node = Node { item, children: Vec::new() };
+                if let Some(&EventType::InScope) = events.get(*pos) {
+                    *pos += 1;
+                    node.children = read_nodes(events, pos);
+                }
+                nodes.push(node);
+            }
+            EventType::InScope => nodes.extend(read_nodes(events, pos)),
+            EventType::OutScope => break,
+            _ => {}
+        }
+    }
+    nodes
+}
+
+// Renders the items as a JSON array nested following the scopes. Each item is an object
+// with the fields of `TypeStruct::json_schema`, except that `parent` is replaced with the
+// array of its `children`.
+#[cfg(feature = "serde")]
+pub fn events_to_tree_json(events: &[EventType]) -> Result<String, ScopeError> {
+    validate_scopes(events)?;
+    // Items only hold strings, numbers and booleans, which can always be written as JSON.
+    Ok(serde_json::to_string(&read_nodes(events, &mut 0)).expect("items always serialize"))
+}
+
+// Only compares the items and the scopes: comments and blank lines are skipped. Since the
+// scopes already give the nesting, the parents of items aren't compared either, only their
+// types, names and args.
+pub fn events_equivalent(a: &[EventType], b: &[EventType]) -> bool {
+    fn structure(events: &[EventType]) -> impl Iterator<Item = &EventType> {
+        events.iter().filter(|e| matches!(*e, EventType::Type(_) | EventType::InScope |
+                                                  EventType::OutScope))
+    }
+
+    let mut a = structure(a);
+    let mut b = structure(b);
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(EventType::Type(x)), Some(EventType::Type(y))) => {
+                if x.ty != y.ty || x.name != y.name || x.args != y.args {
+                    return false;
+                }
+            }
+            (Some(x), Some(y)) if x == y => {}
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ty(ty: Type, name: &str) -> EventType {
+        EventType::Type(TypeStruct::new(ty, name))
+    }
+
+    #[test]
+    fn events_from_tree_round_trip() {
+        let events = vec![
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            ty(Type::Struct, "Foo"),
+            EventType::InScope,
+            EventType::OutScope,
+            ty(Type::Impl, "Foo"),
+            EventType::InScope,
+            ty(Type::Fn, "a"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::OutScope,
+            ty(Type::Impl, "Foo"),
+            EventType::InScope,
+            ty(Type::Fn, "b"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::OutScope,
+            EventType::OutScope,
+            ty(Type::Trait, "T"),
+            EventType::InScope,
+            ty(Type::Fn, "required"),
+            ty(Type::Fn, "provided"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::OutScope,
+        ];
+        let items = build_tree(&events);
+        assert_eq!(items.len(), 9);
+        let rebuilt = events_from_tree(&items).unwrap();
+        assert_eq!(rebuilt.len(), events.len());
+        assert!(validate_scopes(&rebuilt).is_ok());
+        assert!(events_equivalent(&rebuilt, &events));
+        assert_eq!(build_tree(&rebuilt), items);
+    }
+
+    fn round_trip(events: &[EventType]) -> Vec<EventType> {
+        read_events(write_events(events).as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn const_value_round_trip() {
+        let mut max = TypeStruct::new(Type::Const, "MAX");
+        max.type_annotation = Some("u32".to_owned());
+        max.value = Some("100".to_owned());
+        max.visibility = Some("pub(crate)".to_owned());
+        max.span = Some(Span { start: 3, end: 30 });
+        let mut module = TypeStruct::new(Type::Mod, "m");
+        module.visibility = Some("pub".to_owned());
+        max.parent = Some(Box::new(module));
+        let events = vec![EventType::Type(max.clone())];
+        let read = round_trip(&events);
+        assert_eq!(read, events);
+        assert_eq!(read[0].to_string(), events[0].to_string());
+        assert_eq!(events_serialized_len(&events), write_events(&events).len());
+        max.value = Some("101".to_owned());
+        assert_ne!(read, vec![EventType::Type(max)]);
+    }
+
+    #[test]
+    fn file_module_round_trip() {
+        let mut file = TypeStruct::new(Type::Mod, "file");
+        file.inline = false;
+        let events = vec![
+            EventType::Type(file),
+            ty(Type::Mod, "inline"),
+            EventType::InScope,
+            EventType::OutScope,
+        ];
+        let read = round_trip(&events);
+        assert_eq!(read, events);
+        assert!(validate_scopes(&read).is_ok());
+        assert_eq!(events_from_tree(&build_tree(&read)).unwrap(), events);
+        let mut scoped = read;
+        scoped.insert(1, EventType::InScope);
+        scoped.insert(2, EventType::OutScope);
+        assert_eq!(validate_scopes(&scoped), Err(ScopeError::FileModuleScope { at: 1 }));
+    }
+
+    #[test]
+    fn arbitrary_round_trip() {
+        for seed in 0..500u64 {
+            let mut events = (0..20).map(|i| EventType::arbitrary(seed * 20 + i))
+                                    .collect::<Vec<_>>();
+            events.dedup_by(|a, b| match (a, b) {
+                (&mut EventType::Blank(n), &mut EventType::Blank(ref mut m)) => {
+                    *m += n;
+                    true
+                }
+                _ => false,
+            });
+            let written = write_events(&events);
+            assert_eq!(events_serialized_len(&events), written.len());
+            let read = round_trip(&events);
+            assert_eq!(read, events, "seed {}", seed);
+            // `==` doesn't look at docs, spans and default implementations.
+            assert_eq!(write_events(&read), written, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn carriage_return_round_trip() {
+        let events = vec![EventType::Comment("a\r".to_owned())];
+        assert_eq!(round_trip(&events), events);
+    }
+
+    fn comment(text: &str) -> EventType {
+        EventType::Comment(text.to_owned())
+    }
+
+    #[test]
+    fn dedup_item_docs_blocks() {
+        let events = vec![
+            comment("doc"),
+            comment("more"),
+            EventType::Blank(1),
+            comment("doc"),
+            comment("more"),
+            ty(Type::Struct, "S"),
+        ];
+        assert_eq!(dedup_item_docs(events),
+                   vec![comment("doc"), comment("more"), ty(Type::Struct, "S")]);
+        // Repeated lines of a single block are legitimate.
+        let events = || vec![comment("-"), comment("-"), ty(Type::Struct, "S")];
+        assert_eq!(dedup_item_docs(events()), events());
+        // So are different blocks, and the same block on different items.
+        let events = || vec![
+            comment("a"),
+            EventType::Blank(1),
+            comment("b"),
+            ty(Type::Struct, "S"),
+            comment("a"),
+            ty(Type::Struct, "T"),
+        ];
+        assert_eq!(dedup_item_docs(events()), events());
+    }
+
+    #[test]
+    fn count_direct_children_of_impl() {
+        let events = vec![
+            ty(Type::Impl, "Foo"),
+            EventType::InScope,
+            ty(Type::Fn, "a"),
+            EventType::InScope,
+            ty(Type::Closure, ""),
+            EventType::OutScope,
+            comment("b"),
+            ty(Type::Fn, "b"),
+            ty(Type::Fn, "c"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::OutScope,
+            ty(Type::Fn, "after"),
+        ];
+        assert_eq!(count_direct_children(&events, 0), 3);
+        assert_eq!(count_direct_children(&events, 2), 1);
+        assert_eq!(count_direct_children(&events, 7), 0);
+        assert_eq!(count_direct_children(&events, events.len()), 0);
+        assert_eq!(count_direct_children(&events, 100), 0);
+    }
+
+    #[test]
+    fn detect_indent_styles() {
+        let two = "t mod\tm\n{\n  t fn\tf\n  {\n  }\n}\n";
+        let four = two.replace("  ", "    ");
+        let tab = two.replace("  ", "\t");
+        assert_eq!(detect_indent(two), Indent::Spaces(2));
+        assert_eq!(detect_indent(&four), Indent::Spaces(4));
+        assert_eq!(detect_indent(&tab), Indent::Tab);
+        assert_eq!(detect_indent("t fn\tf\n"), Indent::Spaces(INDENT.len()));
+        let events = read_events(four.as_bytes()).unwrap();
+        assert_eq!(write_events(&events), four);
+        assert_eq!(read_events(two.as_bytes()).unwrap(), events);
+        assert_eq!(read_events(tab.as_bytes()).unwrap(), events);
+    }
+
+    #[test]
+    fn retain_types_drops_comments() {
+        let events = vec![
+            EventType::FileComment("file".to_owned()),
+            comment("doc"),
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            EventType::InnerComment("inner".to_owned()),
+            ty(Type::Fn, "f"),
+            EventType::TrailingComment("trailing".to_owned()),
+            EventType::OutScope,
+        ];
+        let types = retain_types(events);
+        assert_eq!(types, vec![ty(Type::Mod, "m"), EventType::InScope, ty(Type::Fn, "f"),
+                               EventType::OutScope]);
+        assert!(validate_scopes(&types).is_ok());
+    }
+
+    #[test]
+    fn extract_comments_targets() {
+        let events = vec![
+            comment("first"),
+            comment("second"),
+            ty(Type::Struct, "S"),
+            comment("dangling"),
+        ];
+        let s = Some(TypeStruct::new(Type::Struct, "S"));
+        assert_eq!(extract_comments(&events), vec![(s.clone(), "first".to_owned()),
+                                                   (s, "second".to_owned()),
+                                                   (None, "dangling".to_owned())]);
+    }
+
+    #[test]
+    fn dedup_file_comments_adjacent() {
+        let file = |c: &str| EventType::FileComment(c.to_owned());
+        assert_eq!(dedup_file_comments(vec![file("a"), file("a"), file("b")]),
+                   vec![file("a"), file("b")]);
+        assert_eq!(dedup_file_comments(vec![file("a"), file("b"), file("a")]),
+                   vec![file("a"), file("b"), file("a")]);
+        // Only file comments are merged.
+        assert_eq!(dedup_file_comments(vec![comment("a"), comment("a")]),
+                   vec![comment("a"), comment("a")]);
+    }
+
+    #[test]
+    fn outline() {
+        let events = vec![
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            comment("doc"),
+            ty(Type::Struct, "S"),
+            ty(Type::Fn, "f"),
+            EventType::InScope,
+            ty(Type::Struct, "Inner"),
+            EventType::OutScope,
+            EventType::OutScope,
+            ty(Type::Fn, "g"),
+            ty(Type::Unknown, "x"),
+            EventType::Type(TypeStruct::new(Type::Unknown, "U").with_raw_keyword("union")),
+        ];
+        assert_eq!(events_to_outline(&events),
+                   "mod m\n    struct S\n    fn f\n        struct Inner\nfn g\nx\nunion U\n");
+    }
+
+    #[test]
+    fn blank_round_trip() {
+        let events = vec![
+            EventType::Blank(1),
+            ty(Type::Fn, "f"),
+            EventType::Blank(2),
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            EventType::Blank(1),
+            EventType::OutScope,
+            EventType::Blank(1),
+        ];
+        let written = write_events(&events);
+        assert_eq!(written, "\nt fn\tf\n\n\nt mod\tm\n{\n\n}\n\n");
+        assert_eq!(events_serialized_len(&events), written.len());
+        assert_eq!(round_trip(&events), events);
+    }
+
+    #[test]
+    fn read_events_lossy_skips_bad_lines() {
+        let input = "t fn\tf\nbad line\nc doc\n";
+        let (events, errors) = read_events_lossy(input.as_bytes());
+        assert_eq!(events, vec![ty(Type::Fn, "f"), comment("doc")]);
+        assert_eq!(errors, vec![ParseError {
+            line: 2,
+            message: "unrecognized line \"bad line\"".to_owned(),
+        }]);
+        assert_eq!(read_events(input.as_bytes()), Err(errors[0].clone()));
+    }
+
+    #[test]
+    fn filter_types_keeps_scopes_valid() {
+        let events = vec![
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            comment("doc"),
+            ty(Type::Fn, "f"),
+            EventType::InScope,
+            ty(Type::Struct, "Inner"),
+            EventType::OutScope,
+            EventType::TrailingComment("trailing".to_owned()),
+            ty(Type::Struct, "S"),
+            ty(Type::Fn, "g"),
+            EventType::OutScope,
+        ];
+        let filtered = filter_types(events, |t| t.ty != Type::Fn);
+        assert_eq!(filtered, vec![ty(Type::Mod, "m"), EventType::InScope,
+                                  ty(Type::Struct, "S"), EventType::OutScope]);
+        assert!(validate_scopes(&filtered).is_ok());
+    }
+
+    #[test]
+    fn statistics() {
+        let events = vec![
+            EventType::FileComment("file".to_owned()),
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            comment("doc"),
+            ty(Type::Fn, "f"),
+            EventType::InScope,
+            EventType::OutScope,
+            ty(Type::Fn, "g"),
+            EventType::OutScope,
+            EventType::Blank(2),
+        ];
+        let stats = events_statistics(&events);
+        assert_eq!(stats.total_events, 10);
+        assert_eq!(stats.per_type,
+                   vec![(Type::Mod, 1), (Type::Fn, 2)].into_iter().collect::<BTreeMap<_, _>>());
+        assert_eq!(stats.comments, 2);
+        assert_eq!(stats.max_depth, 2);
+        assert!(stats.balanced);
+        assert!(!events_statistics(&[EventType::OutScope]).balanced);
+        assert_eq!(events_statistics(&[]), Stats { balanced: true, ..Stats::default() });
+    }
+
+    #[test]
+    fn trailing_comments() {
+        let events = vec![
+            ty(Type::Fn, "f"),
+            EventType::TrailingComment("after f".to_owned()),
+            ty(Type::Struct, "S"),
+            EventType::InScope,
+            EventType::InnerComment("in S".to_owned()),
+            EventType::OutScope,
+            EventType::TrailingComment("after S".to_owned()),
+            ty(Type::Fn, "g"),
+        ];
+        let f = Some(TypeStruct::new(Type::Fn, "f"));
+        let s = Some(TypeStruct::new(Type::Struct, "S"));
+        assert_eq!(extract_comments(&events), vec![(f, "after f".to_owned()),
+                                                   (s.clone(), "in S".to_owned()),
+                                                   (s, "after S".to_owned())]);
+        assert_eq!(round_trip(&events), events);
+    }
+
+    #[test]
+    fn group_by_file_partitions() {
+        let events = vec![
+            EventType::FileComment("crate".to_owned()),
+            EventType::File("a.rs".to_owned()),
+            ty(Type::Fn, "f"),
+            EventType::File("b.rs".to_owned()),
+            comment("doc"),
+            ty(Type::Fn, "g"),
+        ];
+        assert_eq!(group_by_file(events), vec![
+            (String::new(), vec![EventType::FileComment("crate".to_owned())]),
+            ("a.rs".to_owned(), vec![ty(Type::Fn, "f")]),
+            ("b.rs".to_owned(), vec![comment("doc"), ty(Type::Fn, "g")]),
+        ]);
+    }
+
+    #[test]
+    fn span_round_trip() {
+        let mut m = TypeStruct::new(Type::Mod, "m");
+        m.span = Some(Span { start: 0, end: 100 });
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.span = Some(Span { start: 10, end: 20 });
+        f.parent = Some(Box::new(m));
+        let events = vec![EventType::Type(f)];
+        let read = round_trip(&events);
+        let f = match read[0] {
+            EventType::Type(ref f) => f,
+            _ => panic!("expected a type"),
+        };
+        assert_eq!(f.span, Some(Span { start: 10, end: 20 }));
+        assert_eq!(f.parent.as_ref().unwrap().span, Some(Span { start: 0, end: 100 }));
+        assert!(read_events("t fn\tf§@10..x\n".as_bytes()).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tree_json() {
+        use serde_json::{self, Value};
+
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.args.push("(a: \"x\")".to_owned());
+        f.span = Some(Span { start: 3, end: 4 });
+        let events = vec![
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            EventType::Type(f),
+            EventType::InScope,
+            EventType::OutScope,
+            ty(Type::Struct, "S"),
+            EventType::OutScope,
+        ];
+        let tree: Value = serde_json::from_str(&events_to_tree_json(&events).unwrap()).unwrap();
+        let schema: Value = serde_json::from_str(TypeStruct::json_schema()).unwrap();
+        let mut expected = schema["required"].as_array().unwrap().iter()
+                                             .map(|k| k.as_str().unwrap())
+                                             .filter(|&k| k != "parent")
+                                             .chain(Some("children"))
+                                             .collect::<Vec<_>>();
+        let mut keys = tree[0].as_object().unwrap().keys().map(|k| k.as_str())
+                              .collect::<Vec<_>>();
+        expected.sort();
+        keys.sort();
+        assert_eq!(keys, expected);
+        assert_eq!(tree.as_array().unwrap().len(), 1);
+        assert_eq!(tree[0]["ty"], "mod");
+        assert_eq!(tree[0]["name"], "m");
+        let children = tree[0]["children"].as_array().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0]["ty"], "fn");
+        assert_eq!(children[0]["args"][0], "(a: \"x\")");
+        assert_eq!(children[0]["span"]["start"], 3);
+        assert_eq!(children[0]["children"], Value::Array(Vec::new()));
+        assert_eq!(children[1]["name"], "S");
+        assert_eq!(events_to_tree_json(&[EventType::InScope]),
+                   Err(ScopeError::UnclosedScopes { count: 1 }));
+    }
+
+    #[test]
+    fn trait_default_methods() {
+        let events = vec![
+            ty(Type::Trait, "T"),
+            EventType::InScope,
+            ty(Type::Fn, "required"),
+            ty(Type::Fn, "provided"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::OutScope,
+        ];
+        let items = build_tree(&events);
+        assert!(items[1].is_required_method());
+        assert!(!items[1].has_default);
+        assert!(!items[2].is_required_method());
+        assert!(items[2].has_default);
+        assert!(!items[0].is_required_method());
+    }
+
+    #[test]
+    fn equivalent_streams() {
+        let a = vec![comment("doc"), ty(Type::Fn, "f"), ty(Type::Fn, "g")];
+        let b = vec![comment("  doc "), EventType::Blank(1), ty(Type::Fn, "f"), ty(Type::Fn, "g")];
+        assert!(events_equivalent(&a, &b));
+        let reordered = vec![comment("doc"), ty(Type::Fn, "g"), ty(Type::Fn, "f")];
+        assert!(!events_equivalent(&a, &reordered));
+        assert!(!events_equivalent(&a, &a[..2]));
+    }
+
+    #[test]
+    fn remove_nested_scope() {
+        let mut events = vec![
+            ty(Type::Mod, "outer"),
+            EventType::InScope,
+            comment("doc"),
+            ty(Type::Mod, "inner"),
+            EventType::InScope,
+            ty(Type::Fn, "f"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::OutScope,
+            ty(Type::Fn, "g"),
+            EventType::OutScope,
+        ];
+        assert!(remove_scope(&mut events, 3));
+        assert_eq!(events, vec![ty(Type::Mod, "outer"), EventType::InScope, comment("doc"),
+                                ty(Type::Fn, "g"), EventType::OutScope]);
+        assert!(validate_scopes(&events).is_ok());
+        assert!(remove_scope(&mut events, 3));
+        assert_eq!(events, vec![ty(Type::Mod, "outer"), EventType::InScope, comment("doc"),
+                                EventType::OutScope]);
+        // Neither a comment, a scope delimiter nor an out of bounds index removes anything.
+        for &index in &[1, 2, 3, 4, 100] {
+            assert!(!remove_scope(&mut events, index));
+        }
+        assert_eq!(events.len(), 4);
+    }
+
+    #[test]
+    fn sort_functions_with_docs() {
+        let events = vec![
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            comment("c doc"),
+            ty(Type::Fn, "c"),
+            comment("a doc"),
+            ty(Type::Fn, "a"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::TrailingComment("after a".to_owned()),
+            comment("b doc"),
+            ty(Type::Fn, "b"),
+            EventType::OutScope,
+        ];
+        assert_eq!(sort_within_scopes(events), vec![
+            ty(Type::Mod, "m"),
+            EventType::InScope,
+            comment("a doc"),
+            ty(Type::Fn, "a"),
+            EventType::InScope,
+            EventType::OutScope,
+            EventType::TrailingComment("after a".to_owned()),
+            comment("b doc"),
+            ty(Type::Fn, "b"),
+            comment("c doc"),
+            ty(Type::Fn, "c"),
+            EventType::OutScope,
+        ]);
+    }
+
+    #[test]
+    fn sort_impls_by_target() {
+        let events = vec![
+            EventType::Type(TypeStruct::trait_impl("Display", "Foo")),
+            ty(Type::Struct, "Foo"),
+            EventType::Type(TypeStruct::inherent_impl("Foo")),
+            ty(Type::Struct, "Bar"),
+            EventType::Type(TypeStruct::inherent_impl("Bar")),
+            ty(Type::Fn, "a"),
+        ];
+        assert_eq!(sort_within_scopes(events), vec![
+            ty(Type::Struct, "Bar"),
+            EventType::Type(TypeStruct::inherent_impl("Bar")),
+            EventType::Type(TypeStruct::trait_impl("Display", "Foo")),
+            ty(Type::Struct, "Foo"),
+            EventType::Type(TypeStruct::inherent_impl("Foo")),
+            ty(Type::Fn, "a"),
+        ]);
+    }
+
+    #[test]
+    fn concat_fragments() {
+        let fragment = || vec![ty(Type::Mod, "m"), EventType::InScope, EventType::OutScope];
+        let events = concat_events(vec![fragment(), fragment()]).unwrap();
+        assert_eq!(events.len(), 6);
+        assert!(validate_scopes(&events).is_ok());
+        assert_eq!(concat_events(vec![fragment(), vec![EventType::OutScope], fragment()]),
+                   Err(ScopeError::InvalidFragment { fragment: 1 }));
+    }
+
+    #[test]
+    fn events_from_tree_unscoped_children() {
+        let mut file = TypeStruct::new(Type::Mod, "m");
+        file.inline = false;
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.parent = Some(Box::new(file.clone()));
+        assert_eq!(events_from_tree(&[file, f]), Err(ScopeError::UnscopedChildren { item: 0 }));
+        let t = TypeStruct::new(Type::Trait, "T");
+        let mut g = TypeStruct::new(Type::Fn, "g");
+        g.parent = Some(Box::new(t.clone()));
+        let mut bound = TypeStruct::new(Type::Bound, "U");
+        bound.parent = Some(Box::new(g.clone()));
+        assert_eq!(events_from_tree(&[t, g, bound]),
+                   Err(ScopeError::UnscopedChildren { item: 1 }));
+        assert_eq!(ScopeError::UnscopedChildren { item: 1 }.to_string(),
+                   "item 1 has children but can't have a scope");
+    }
+
+    #[test]
+    fn read_events_lossy_skips_invalid_utf8() {
+        let input = &b"c \xff\nc ok\n"[..];
+        let (events, errors) = read_events_lossy(input);
+        assert_eq!(events, vec![comment("ok")]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 1);
+        assert_eq!(read_events(input).unwrap_err().line, 1);
+    }
+}
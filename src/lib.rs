@@ -12,23 +12,73 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
 pub use self::consts::{
     FILE,
     FILE_COMMENT,
     MOD_COMMENT,
     END_INFO,
 };
+pub use self::errors::{
+    NameError,
+    ParseError,
+    ScopeError,
+};
+pub use self::events::{
+    build_tree,
+    concat_events,
+    count_direct_children,
+    dedup_file_comments,
+    dedup_item_docs,
+    detect_indent,
+    events_equivalent,
+    events_from_tree,
+    events_serialized_len,
+    events_statistics,
+    events_to_outline,
+    extract_comments,
+    filter_types,
+    group_by_file,
+    Indent,
+    read_events,
+    read_events_lossy,
+    remove_scope,
+    retain_types,
+    sort_within_scopes,
+    Stats,
+    validate_scopes,
+    write_events,
+};
+#[cfg(feature = "serde")]
+pub use self::events::events_to_tree_json;
 pub use self::types::{
+    Category,
+    CommentPosition,
+    depth_histogram,
     EventType,
+    ImplKind,
+    MacroKind,
+    PlainTheme,
+    Span,
+    Theme,
     Type,
+    TypeRegistry,
     TypeStruct,
 };
 pub use self::utils::{
+    reflow_comment,
+    rewrite_doc_links,
     write_comment,
     write_file,
     write_file_comment,
 };
 
 mod consts;
+mod errors;
+mod events;
 mod types;
 mod utils;
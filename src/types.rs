@@ -13,22 +13,365 @@
 // limitations under the License.
 
 use std::cmp::PartialEq;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{Debug, Display, Formatter, Error};
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
 
+use consts::INDENT;
+use errors::NameError;
+
+// Boxing `TypeStruct` would make every `EventType::Type` match more awkward for little gain:
+// most events of a stream are types anyway.
+#[allow(clippy::large_enum_variant)]
+#[derive(PartialEq, Eq, Hash)]
 pub enum EventType {
     Comment(String),
+    /// A comment on the same line as the end of the item before it.
+    TrailingComment(String),
+    /// A comment inside of a scope, documenting the item owning it.
+    InnerComment(String),
     FileComment(String),
+    /// Marks the start of the events of the file at the given path.
+    File(String),
     Type(TypeStruct),
     InScope,
     OutScope,
+    /// A run of blank lines between items.
+    Blank(usize),
+}
+
+impl EventType {
+    pub fn is_documentation(&self) -> bool {
+        matches!(*self, EventType::Comment(_) | EventType::TrailingComment(_) |
+                        EventType::InnerComment(_) | EventType::FileComment(_))
+    }
+
+    // File comments have no position since they aren't attached to an item.
+    pub fn comment_position(&self) -> Option<CommentPosition> {
+        match *self {
+            EventType::Comment(_) => Some(CommentPosition::Leading),
+            EventType::TrailingComment(_) => Some(CommentPosition::Trailing),
+            EventType::InnerComment(_) => Some(CommentPosition::Inner),
+            _ => None,
+        }
+    }
+
+    pub fn as_mut_type(&mut self) -> Option<&mut TypeStruct> {
+        match *self {
+            EventType::Type(ref mut t) => Some(t),
+            _ => None,
+        }
+    }
+
+    // `indent` is the scope depth of the event. Blank lines are never indented.
+    pub fn render_line(&self, indent: usize) -> String {
+        match *self {
+            EventType::Blank(_) => String::new(),
+            _ => format!("{}{}", INDENT.repeat(indent), self),
+        }
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        match *self {
+            EventType::Comment(ref c) |
+            EventType::TrailingComment(ref c) |
+            EventType::InnerComment(ref c) |
+            EventType::FileComment(ref c) |
+            EventType::File(ref c) => 2 + escaped_len(c),
+            EventType::Type(ref t) => {
+                let mut len = 2;
+                for (pos, segment) in t.ancestors().into_iter().chain(Some(t)).enumerate() {
+                    if pos > 0 {
+                        len += '§'.len_utf8();
+                    }
+                    len += segment.ty.as_str().len() + 1 + escaped_len(&segment.name);
+                    len += segment.args.iter().map(|a| 1 + escaped_len(a)).sum::<usize>();
+                    if let Some(span) = segment.span {
+                        len += '§'.len_utf8() + format!("@{}..{}", span.start, span.end).len();
+                    }
+                    for (key, value) in stream_attributes(segment) {
+                        len += '§'.len_utf8() + 1 + key.len();
+                        len += value.map_or(0, |v| 1 + escaped_len(&v));
+                    }
+                }
+                len
+            }
+            EventType::InScope | EventType::OutScope => 1,
+            EventType::Blank(_) => 0,
+        }
+    }
+}
+
+// Generates an event with every field of `TypeStruct`s set at random, which `read_events`
+// reads back as is from its `write_events` output. Consecutive `Blank` events are merged when
+// read back though.
+#[cfg(test)]
+impl EventType {
+    pub fn arbitrary(seed: u64) -> EventType {
+        let mut rng = Rng::new(seed);
+        match rng.below(9) {
+            0 => EventType::Comment(rng.text()),
+            1 => EventType::TrailingComment(rng.text()),
+            2 => EventType::InnerComment(rng.text()),
+            3 => EventType::FileComment(rng.text()),
+            4 => EventType::File(rng.text()),
+            5 => EventType::InScope,
+            6 => EventType::OutScope,
+            7 => EventType::Blank(1 + rng.below(3)),
+            _ => {
+                let mut parent = None;
+                for _ in 0..=rng.below(3) {
+                    let mut t = rng.type_struct();
+                    t.parent = parent.map(Box::new);
+                    parent = Some(t);
+                }
+                EventType::Type(parent.unwrap())
+            }
+        }
+    }
+}
+
+// xorshift64*, good enough to generate test inputs.
+#[cfg(test)]
+struct Rng(u64);
+
+#[cfg(test)]
+impl Rng {
+    // The seed is scrambled with splitmix64 so close seeds don't give close sequences.
+    fn new(seed: u64) -> Rng {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        // xorshift gets stuck on 0.
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn flip(&mut self) -> bool {
+        self.below(2) == 0
+    }
+
+    // Mixes in the characters the stream format has to escape or uses as separators.
+    fn text(&mut self) -> String {
+        const CHARS: &[char] = &['a', 'Z', '_', '0', ' ', ':', '{', '}', 'é', '\\', '\n', '\r',
+                                 '\t', '§', '@', '+', '=', '.'];
+        (0..self.below(8)).map(|_| CHARS[self.below(CHARS.len())]).collect()
+    }
+
+    fn maybe_text(&mut self) -> Option<String> {
+        if self.flip() {
+            Some(self.text())
+        } else {
+            None
+        }
+    }
+
+    // Without its parent.
+    fn type_struct(&mut self) -> TypeStruct {
+        let mut t = TypeStruct::new(Type::all()[self.below(Type::all().len())], &self.text());
+        for _ in 0..self.below(3) {
+            t.args.push(self.text());
+        }
+        t.inline = self.flip();
+        for _ in 0..self.below(3) {
+            t.docs.push(self.text());
+        }
+        t.raw_keyword = self.maybe_text();
+        if self.flip() {
+            t.variant_count = Some(self.below(100));
+        }
+        t.impl_kind = [None, Some(ImplKind::Inherent), Some(ImplKind::Trait)][self.below(3)];
+        t.type_annotation = self.maybe_text();
+        t.discriminant = self.maybe_text();
+        t.visibility = self.maybe_text();
+        if self.flip() {
+            let start = self.below(1000);
+            t.span = Some(Span { start, end: start + self.below(1000) });
+        }
+        t.has_default = self.flip();
+        t.macro_kind = [None, Some(MacroKind::Rules), Some(MacroKind::Decl2),
+                        Some(MacroKind::Proc)][self.below(4)];
+        t.value = self.maybe_text();
+        t
+    }
+}
+
+fn escaped_len(s: &str) -> usize {
+    s.chars().map(|c| match c {
+        '\\' | '\n' | '\r' | '\t' | '§' => 2,
+        c => c.len_utf8(),
+    }).sum()
+}
+
+fn write_escaped(f: &mut Formatter, s: &str) -> Result<(), Error> {
+    for c in s.chars() {
+        match c {
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            '§' => write!(f, "\\s")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+// The fields of `t` which are written as `+key=value` segments in the stream, flags having no
+// value. `set_stream_attribute` reads them back.
+fn stream_attributes(t: &TypeStruct) -> Vec<(&'static str, Option<String>)> {
+    let mut attributes = Vec::new();
+    if !t.inline {
+        attributes.push(("file", None));
+    }
+    if t.has_default {
+        attributes.push(("default", None));
+    }
+    if let Some(ref visibility) = t.visibility {
+        attributes.push(("vis", Some(visibility.clone())));
+    }
+    if let Some(ref raw) = t.raw_keyword {
+        attributes.push(("raw", Some(raw.clone())));
+    }
+    if let Some(count) = t.variant_count {
+        attributes.push(("variants", Some(count.to_string())));
+    }
+    if let Some(kind) = t.impl_kind {
+        attributes.push(("impl", Some(kind.as_str().to_owned())));
+    }
+    if let Some(ref annotation) = t.type_annotation {
+        attributes.push(("type", Some(annotation.clone())));
+    }
+    if let Some(ref discriminant) = t.discriminant {
+        attributes.push(("discriminant", Some(discriminant.clone())));
+    }
+    if let Some(kind) = t.macro_kind {
+        attributes.push(("macro", Some(kind.as_str().to_owned())));
+    }
+    if let Some(ref value) = t.value {
+        attributes.push(("value", Some(value.clone())));
+    }
+    for doc in &t.docs {
+        attributes.push(("doc", Some(doc.clone())));
+    }
+    attributes
+}
+
+// Not exported, used by the stream reader in `events`. `value` is already unescaped.
+pub fn set_stream_attribute(t: &mut TypeStruct, key: &str,
+                            value: Option<String>) -> Result<(), String> {
+    match (key, value) {
+        ("file", None) => t.inline = false,
+        ("default", None) => t.has_default = true,
+        ("vis", Some(value)) => t.visibility = Some(value),
+        ("raw", Some(value)) => t.raw_keyword = Some(value),
+        ("variants", Some(value)) => match value.parse() {
+            Ok(count) => t.variant_count = Some(count),
+            Err(_) => return Err(format!("invalid variant count \"{}\"", value)),
+        },
+        ("impl", Some(value)) => t.impl_kind = Some(match value.as_str() {
+            "inherent" => ImplKind::Inherent,
+            "trait" => ImplKind::Trait,
+            _ => return Err(format!("invalid impl kind \"{}\"", value)),
+        }),
+        ("type", Some(value)) => t.type_annotation = Some(value),
+        ("discriminant", Some(value)) => t.discriminant = Some(value),
+        ("macro", Some(value)) => match MacroKind::from_keyword(&value) {
+            Some(kind) => t.macro_kind = Some(kind),
+            None => return Err(format!("invalid macro kind \"{}\"", value)),
+        },
+        ("value", Some(value)) => t.value = Some(value),
+        ("doc", Some(value)) => t.docs.push(value),
+        (key, _) => return Err(format!("invalid attribute \"{}\"", key)),
+    }
+    Ok(())
+}
+
+// Renders the event on a single line:
+//
+// * `c <text>` for a comment, `r <text>` for a trailing one, `i <text>` for an inner one and
+//   `f <text>` for a file comment.
+// * `p <path>` for the start of a file.
+// * `t <segments>` for a type, where each level of the parent chain (root first) is a
+//   `ty\tname\targ...` segment, followed by an `@start..end` segment for its span and by
+//   `+key=value` segments for its other fields (see `stream_attributes`). Segments are
+//   separated by `§`.
+// * `{` and `}` for `InScope` and `OutScope`.
+// * nothing for `Blank`, which `write_events` emits as that many empty lines.
+//
+// Backslashes, newlines, carriage returns, tabs and `§` are escaped as `\\`, `\n`, `\r`, `\t`
+// and `\s`.
+impl Display for EventType {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            EventType::Comment(ref c) => {
+                write!(f, "c ")?;
+                write_escaped(f, c)
+            }
+            EventType::TrailingComment(ref c) => {
+                write!(f, "r ")?;
+                write_escaped(f, c)
+            }
+            EventType::InnerComment(ref c) => {
+                write!(f, "i ")?;
+                write_escaped(f, c)
+            }
+            EventType::FileComment(ref c) => {
+                write!(f, "f ")?;
+                write_escaped(f, c)
+            }
+            EventType::File(ref path) => {
+                write!(f, "p ")?;
+                write_escaped(f, path)
+            }
+            EventType::Type(ref t) => {
+                write!(f, "t ")?;
+                for (pos, segment) in t.ancestors().into_iter().chain(Some(t)).enumerate() {
+                    if pos > 0 {
+                        write!(f, "§")?;
+                    }
+                    write!(f, "{}\t", segment.ty.as_str())?;
+                    write_escaped(f, &segment.name)?;
+                    for arg in &segment.args {
+                        write!(f, "\t")?;
+                        write_escaped(f, arg)?;
+                    }
+                    if let Some(span) = segment.span {
+                        write!(f, "§@{}..{}", span.start, span.end)?;
+                    }
+                    for (key, value) in stream_attributes(segment) {
+                        write!(f, "§+{}", key)?;
+                        if let Some(value) = value {
+                            write!(f, "=")?;
+                            write_escaped(f, &value)?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            EventType::InScope => write!(f, "{{"),
+            EventType::OutScope => write!(f, "}}"),
+            EventType::Blank(_) => Ok(()),
+        }
+    }
 }
 
 impl Debug for EventType {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
-        match self {
-            &EventType::Type(ref t) => writeln!(fmt, "{}", t),
+        match *self {
+            EventType::Type(ref t) => writeln!(fmt, "{}", t),
             _ => Ok(())
         }
     }
@@ -37,20 +380,198 @@ impl Debug for EventType {
 pub struct TypeStruct {
     pub ty: Type,
     pub parent: Option<Box<TypeStruct>>,
-    pub name: String,
+    pub name: Cow<'static, str>,
     pub args: Vec<String>,
+    /// Only meaningful for `Type::Mod`: `false` for `mod m;` which has no scope of its own.
+    pub inline: bool,
+    pub docs: Vec<String>,
+    /// The keyword as it was found in the source. Rendered as the keyword of `Type::Unknown`
+    /// items.
+    pub raw_keyword: Option<String>,
+    pub variant_count: Option<usize>,
+    /// Only set for `Type::Impl`.
+    pub impl_kind: Option<ImplKind>,
+    /// The type of a `Type::Const` or `Type::Static`, or the aliased type of a `Type::Type`.
+    pub type_annotation: Option<String>,
+    /// The explicit discriminant of a `Type::Variant` (`Foo = 1`).
+    pub discriminant: Option<String>,
+    /// The visibility as written in the source, like `pub` or `pub(crate)`. `None` when private.
+    pub visibility: Option<String>,
+    /// Byte range of the item in its source file.
+    pub span: Option<Span>,
+    /// Only meaningful for trait methods: whether they have a default implementation.
+    pub has_default: bool,
+    /// Only set for `Type::Macro`.
+    pub macro_kind: Option<MacroKind>,
+    /// The initializer of constants and statics.
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    /// Exclusive.
+    pub end: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CommentPosition {
+    /// Before the item.
+    Leading,
+    /// After the item, on the same line.
+    Trailing,
+    /// Inside of the item's scope.
+    Inner,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Items holding other items: modules, traits and impls.
+    Container,
+    /// Functions, closures, generators and macros.
+    Callable,
+    /// Types and values: structs, enums, variants, type aliases, constants and statics.
+    Data,
+    /// `use` items.
+    Reexport,
+    /// Bounds, `dyn` and `impl` types and unknown items, which come from the stripper rather
+    /// than from an item of the source.
+    Synthetic,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MacroKind {
+    /// `macro_rules!`.
+    Rules,
+    /// `macro`, from declarative macros 2.0.
+    Decl2,
+    /// `#[proc_macro]`, `#[proc_macro_derive]` and `#[proc_macro_attribute]`.
+    Proc,
+}
+
+impl MacroKind {
+    pub fn from_keyword(keyword: &str) -> Option<MacroKind> {
+        match keyword {
+            "macro_rules" | "macro_rules!" => Some(MacroKind::Rules),
+            "macro" => Some(MacroKind::Decl2),
+            "proc_macro" | "proc_macro_derive" | "proc_macro_attribute" => Some(MacroKind::Proc),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            MacroKind::Rules => "macro_rules!",
+            MacroKind::Decl2 => "macro",
+            MacroKind::Proc => "proc_macro",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ImplKind {
+    Inherent,
+    Trait,
+}
+
+impl ImplKind {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ImplKind::Inherent => "inherent",
+            ImplKind::Trait => "trait",
+        }
+    }
 }
 
+// Describes the output of the `Serialize` implementation of `TypeStruct`.
+#[cfg(feature = "serde")]
+const TYPE_STRUCT_JSON_SCHEMA: &str = r##"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "TypeStruct",
+  "type": "object",
+  "properties": {
+    "ty": {
+      "enum": ["struct", "mod", "enum", "fn", "const", "static", "type", "variant", "impl",
+               "use", "macro", "trait", "bound", "closure", "dyn", "impl_trait", "gen", "?"]
+    },
+    "name": { "type": "string" },
+    "args": { "type": "array", "items": { "type": "string" } },
+    "parent": { "oneOf": [{ "$ref": "#" }, { "type": "null" }] },
+    "inline": { "type": "boolean" },
+    "docs": { "type": "array", "items": { "type": "string" } },
+    "raw_keyword": { "type": ["string", "null"] },
+    "variant_count": { "type": ["integer", "null"], "minimum": 0 },
+    "impl_kind": { "enum": ["inherent", "trait", null] },
+    "type_annotation": { "type": ["string", "null"] },
+    "discriminant": { "type": ["string", "null"] },
+    "visibility": { "type": ["string", "null"] },
+    "span": {
+      "oneOf": [
+        {
+          "type": "object",
+          "properties": {
+            "start": { "type": "integer", "minimum": 0 },
+            "end": { "type": "integer", "minimum": 0 }
+          },
+          "required": ["start", "end"],
+          "additionalProperties": false
+        },
+        { "type": "null" }
+      ]
+    },
+    "has_default": { "type": "boolean" },
+    "macro_kind": { "enum": ["macro_rules!", "macro", "proc_macro", null] },
+    "value": { "type": ["string", "null"] }
+  },
+  "required": ["ty", "name", "args", "parent", "inline", "docs", "raw_keyword", "variant_count",
+               "impl_kind", "type_annotation", "discriminant", "visibility", "span",
+               "has_default", "macro_kind", "value"],
+  "additionalProperties": false
+}"##;
+
 impl TypeStruct {
     pub fn new(ty: Type, name: &str) -> TypeStruct {
         TypeStruct {
-            ty: ty,
-            name: name.to_owned(),
+            ty,
+            name: Cow::Owned(name.to_owned()),
             args: vec!(),
             parent: None,
+            inline: true,
+            docs: Vec::new(),
+            raw_keyword: None,
+            variant_count: None,
+            impl_kind: None,
+            type_annotation: None,
+            discriminant: None,
+            visibility: None,
+            span: None,
+            has_default: false,
+            macro_kind: None,
+            value: None,
         }
     }
 
+    // Doesn't allocate for the name.
+    pub fn from_static(ty: Type, name: &'static str) -> TypeStruct {
+        let mut t = TypeStruct::new(ty, "");
+        t.name = Cow::Borrowed(name);
+        t
+    }
+
+    pub fn inherent_impl(for_type: &str) -> TypeStruct {
+        let mut t = TypeStruct::new(Type::Impl, "");
+        t.args.push(for_type.to_owned());
+        t.impl_kind = Some(ImplKind::Inherent);
+        t
+    }
+
+    pub fn trait_impl(trait_name: &str, for_type: &str) -> TypeStruct {
+        let mut t = TypeStruct::new(Type::Impl, "");
+        t.args = vec![trait_name.to_owned(), "for".to_owned(), for_type.to_owned()];
+        t.impl_kind = Some(ImplKind::Trait);
+        t
+    }
+
     /*pub fn from_args(ty: Type, args: Vec<String>) -> TypeStruct {
         TypeStruct {
             ty: ty,
@@ -63,16 +584,32 @@ impl TypeStruct {
     pub fn empty() -> TypeStruct {
         TypeStruct {
             ty: Type::Unknown,
-            name: String::new(),
+            name: Cow::Borrowed(""),
             args: Vec::new(),
             parent: None,
+            inline: true,
+            docs: Vec::new(),
+            raw_keyword: None,
+            variant_count: None,
+            impl_kind: None,
+            type_annotation: None,
+            discriminant: None,
+            visibility: None,
+            span: None,
+            has_default: false,
+            macro_kind: None,
+            value: None,
         }
     }
 
+    pub fn into_parts(self) -> (Type, String, Vec<String>, Option<Box<TypeStruct>>) {
+        (self.ty, self.name.into_owned(), self.args, self.parent)
+    }
+
     pub fn get_depth(&self, ignore_macros: bool) -> usize {
         fn recur(ty: &Option<Box<TypeStruct>>, is_parent: bool, ignore_macros: bool) -> usize {
-            match ty {
-                &Some(ref t) => {
+            match *ty {
+                Some(ref t) => {
                     if ignore_macros && is_parent && t.ty == Type::Macro {
                         recur(&t.parent, true, ignore_macros)
                     } else {
@@ -84,138 +621,2487 @@ impl TypeStruct {
         }
         recur(&self.parent, false, ignore_macros)
     }
-}
 
-impl PartialEq for TypeStruct {
-    fn eq(&self, other: &TypeStruct) -> bool {
-        self.ty == other.ty &&
-        self.name == other.name &&
-        self.args == other.args &&
-        self.parent == other.parent
+    // Always `None` if `self` isn't an enum.
+    pub fn variant_count(&self) -> Option<usize> {
+        if self.ty == Type::Enum {
+            self.variant_count
+        } else {
+            None
+        }
     }
 
-    fn ne(&self, other: &TypeStruct) -> bool {
-        !self.eq(other)
+    // FNV-1a over every field compared by `PartialEq`, from the root of the parent chain
+    // down to `self`. Unlike `Hash`, the result doesn't depend on the Rust version or the
+    // platform. Types are written as their `checksum_tag`, strings are prefixed by their length
+    // and optional fields by whether they're set, so fields can't bleed into each other.
+    pub fn checksum_stable(&self) -> u64 {
+        fn write(hash: &mut u64, bytes: &[u8]) {
+            for &b in bytes {
+                *hash ^= u64::from(b);
+                *hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        fn write_str(hash: &mut u64, s: &str) {
+            write(hash, &(s.len() as u64).to_le_bytes());
+            write(hash, s.as_bytes());
+        }
+        fn write_opt(hash: &mut u64, s: Option<&str>) {
+            match s {
+                Some(s) => {
+                    write(hash, &[1]);
+                    write_str(hash, s);
+                }
+                None => write(hash, &[0]),
+            }
+        }
+
+        let mut hash = 0xcbf2_9ce4_8422_2325;
+        for t in self.ancestors().into_iter().chain(Some(self)) {
+            write(&mut hash, &[t.ty.checksum_tag()]);
+            write_str(&mut hash, &t.name);
+            write(&mut hash, &(t.args.len() as u64).to_le_bytes());
+            for arg in &t.args {
+                write_str(&mut hash, arg);
+            }
+            write(&mut hash, &[t.inline as u8]);
+            write_opt(&mut hash, t.raw_keyword.as_deref());
+            match t.variant_count {
+                Some(count) => {
+                    write(&mut hash, &[1]);
+                    write(&mut hash, &(count as u64).to_le_bytes());
+                }
+                None => write(&mut hash, &[0]),
+            }
+            write(&mut hash, &[match t.impl_kind {
+                None => 0,
+                Some(ImplKind::Inherent) => 1,
+                Some(ImplKind::Trait) => 2,
+            }]);
+            write_opt(&mut hash, t.type_annotation.as_deref());
+            write_opt(&mut hash, t.discriminant.as_deref());
+            write_opt(&mut hash, t.visibility.as_deref());
+            write(&mut hash, &[match t.macro_kind {
+                None => 0,
+                Some(MacroKind::Rules) => 1,
+                Some(MacroKind::Decl2) => 2,
+                Some(MacroKind::Proc) => 3,
+            }]);
+            write_opt(&mut hash, t.value.as_deref());
+        }
+        hash
     }
-}
 
-impl Clone for TypeStruct {
-    fn clone(&self) -> TypeStruct {
-        TypeStruct {
-            ty: self.ty,
-            name: self.name.clone(),
-            args: self.args.clone(),
-            parent: match self.parent {
-                Some(ref p) => Some(Box::new(p.deref().clone())),
-                None => None,
+    #[cfg(feature = "serde")]
+    pub fn json_schema() -> &'static str {
+        TYPE_STRUCT_JSON_SCHEMA
+    }
+
+    pub fn args_iter<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+        self.args.iter().map(|a| a.as_str())
+    }
+
+    pub fn retain_args<F: Fn(&str) -> bool>(&mut self, pred: F) {
+        self.args.retain(|a| pred(a));
+    }
+
+    // Removes every arg which already appeared earlier, not only the consecutive ones.
+    pub fn dedup_args(&mut self) {
+        let mut seen = BTreeSet::new();
+        self.args.retain(|a| seen.insert(a.clone()));
+    }
+
+    // Returns the parents of `self`, starting from the root.
+    fn ancestors(&self) -> Vec<&TypeStruct> {
+        let mut ancestors = Vec::new();
+        let mut current = &self.parent;
+        while let Some(ref p) = *current {
+            ancestors.push(p.deref());
+            current = &p.parent;
+        }
+        ancestors.reverse();
+        ancestors
+    }
+
+    // Calls `f` on `self` and then on each of its parents, up to the root.
+    pub fn walk<F: FnMut(&TypeStruct)>(&self, mut f: F) {
+        let mut current = Some(self);
+        while let Some(t) = current {
+            f(t);
+            current = t.parent.as_deref();
+        }
+    }
+
+    // Returns the closest ancestor of type `ty`.
+    pub fn parent_of_type_mut(&mut self, ty: Type) -> Option<&mut TypeStruct> {
+        let mut current = self.parent.as_deref_mut();
+        while let Some(p) = current {
+            if p.ty == ty {
+                return Some(p);
             }
+            current = p.parent.as_deref_mut();
         }
+        None
     }
 
-    fn clone_from(&mut self, source: &TypeStruct) {
-        self.ty = source.ty;
-        self.name = source.name.clone();
-        self.args = source.args.clone();
-        self.parent = match source.parent {
-            Some(ref p) => Some(Box::new(p.deref().clone())),
-            None => None,
-        };
+    pub fn shared_prefix(a: &TypeStruct, b: &TypeStruct) -> Vec<TypeStruct> {
+        a.ancestors().into_iter()
+                     .zip(b.ancestors())
+                     .take_while(|&(x, y)| x == y)
+                     .map(|(x, _)| x.clone())
+                     .collect()
     }
-}
 
-impl Debug for TypeStruct {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let parent = &self.parent;
-        match parent {
-            &Some(ref p) => write!(f, "{:?}§{} {}{}", p, self.ty, self.name, self.args.join(" ")),
-            _ => write!(f, "{} {}{}", self.ty, self.name, self.args.join(" ")),
+    pub fn common_root(items: &[&TypeStruct]) -> Option<TypeStruct> {
+        let mut items = items.iter();
+        let mut prefix = items.next()?.ancestors();
+        for item in items {
+            let shared = prefix.iter()
+                               .zip(item.ancestors())
+                               .take_while(|&(x, y)| *x == y)
+                               .count();
+            prefix.truncate(shared);
         }
+        prefix.last().map(|&root| root.clone())
     }
-}
 
-fn show(f: &mut Formatter, t: &TypeStruct, is_parent: bool) -> Result<(), Error> {
-    if is_parent {
-        write!(f, "{} {}{}§", t.ty, t.name, t.args.join(" "))
-    } else {
-        write!(f, "{} {}{}", t.ty, t.name, t.args.join(" "))
+    // The paths of the parents are included too. Unnamed items, such as inherent impls, have no
+    // path of their own.
+    pub fn flatten_to_paths(items: &[TypeStruct]) -> Vec<String> {
+        let mut paths = BTreeSet::new();
+        for item in items {
+            item.walk(|t| {
+                if !t.name.is_empty() {
+                    paths.insert(t.full_path());
+                }
+            });
+        }
+        paths.into_iter().collect()
     }
-}
 
-fn sub_call(f: &mut Formatter, t: &TypeStruct, is_parent: bool) -> Result<(), Error> {
-    if t.ty == Type::Macro && is_parent == true {
-        match t.parent {
-            Some(ref p) => sub_call(f, p.borrow(), true),
-            _ => Ok(()),
+    // Generics are part of the args so they're removed as well. Parents keep theirs.
+    pub fn without_args(&self) -> TypeStruct {
+        let mut t = self.clone();
+        t.args.clear();
+        t
+    }
+
+    pub fn truncate_to_depth(&self, n: usize) -> TypeStruct {
+        let mut truncated = self.clone();
+        {
+            let mut current = &mut truncated.parent;
+            for _ in 0..n {
+                match *current {
+                    Some(ref mut p) => current = &mut p.parent,
+                    None => break,
+                }
+            }
+            *current = None;
         }
-    } else {
-        match t.parent {
-            Some(ref p) => {
-                try!(sub_call(f, p.borrow(), true));
-                show(f, t, is_parent)
-            },
-            _ => show(f, t, is_parent),
+        truncated
+    }
+
+    // `max_width` is a number of characters. Too long items get one parameter per line, the
+    // return type staying after the closing parenthesis. Items without parameters are kept
+    // on one line.
+    pub fn display_wrapped(&self, max_width: usize) -> String {
+        let inline = self.to_string();
+        if inline.chars().count() <= max_width {
+            return inline;
         }
+        let sig = self.args.join(" ");
+        let params_start = match top_level_find(&sig, '(') {
+            Some(pos) => pos,
+            None => return inline,
+        };
+        let content = bracket_content(&sig[params_start..]);
+        let params = split_top_level(content, |c| c == ',');
+        if params.is_empty() {
+            return inline;
+        }
+        let mut head = self.clone();
+        head.args.clear();
+        let mut out = format!("{}{}(", head, &sig[..params_start]);
+        for param in &params {
+            out.push_str("\n    ");
+            out.push_str(param);
+            out.push(',');
+        }
+        out.push_str("\n)");
+        out.push_str(sig.get(params_start + content.len() + 2..).unwrap_or(""));
+        out
     }
-}
 
-impl Display for TypeStruct {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        sub_call(f, self, false)
+    // Unnamed items (like most impls) don't appear in the path.
+    pub fn full_path(&self) -> String {
+        self.ancestors().into_iter()
+                        .chain(Some(self))
+                        .filter(|t| !t.name.is_empty())
+                        .map(|t| t.name.as_ref())
+                        .collect::<Vec<_>>()
+                        .join("::")
     }
-}
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum Type {
-    Struct,
-    Mod,
-    Enum,
-    Fn,
-    Const,
-    Static,
-    Type,
-    Variant,
-    Impl,
-    Use,
-    Macro,
-    Trait,
-    Unknown,
-}
+    // Falls back to the full path when `root` isn't one of the ancestors.
+    pub fn display_relative_to(&self, root: &TypeStruct) -> String {
+        let ancestors = self.ancestors();
+        let below = match ancestors.iter().position(|&a| a == root) {
+            Some(pos) => &ancestors[pos + 1..],
+            None => return self.full_path(),
+        };
+        below.iter()
+             .cloned()
+             .chain(Some(self))
+             .filter(|t| !t.name.is_empty())
+             .map(|t| t.name.as_ref())
+             .collect::<Vec<_>>()
+             .join("::")
+    }
 
-impl Type {
-    pub fn from(s: &str) -> Type {
-        match s {
-            "struct" => Type::Struct,
-            "mod" => Type::Mod,
-            "enum" => Type::Enum,
-            "fn" => Type::Fn,
-            "const" => Type::Const,
-            "static" => Type::Static,
-            "type" => Type::Type,
-            "impl" => Type::Impl,
-            "use" => Type::Use,
-            "trait" => Type::Trait,
-            "macro" | "macro_rules" | "macro_rules!" => Type::Macro,
-            _ => Type::Variant,
+    // Only the name of `self` is compared loosely, parents still have to be strictly equal.
+    // Everything else is compared by `PartialEq`, on copies with lowercased names.
+    pub fn eq_ignore_name_case(&self, other: &TypeStruct) -> bool {
+        fn lowercased(t: &TypeStruct) -> TypeStruct {
+            let mut t = t.clone();
+            t.name = Cow::Owned(t.name.to_lowercase());
+            t
         }
+        lowercased(self) == lowercased(other)
     }
-}
 
-impl Display for Type {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        match *self {
-            Type::Struct => write!(f, "struct"),
-            Type::Mod => write!(f, "mod"),
-            Type::Enum => write!(f, "enum"),
-            Type::Fn => write!(f, "fn"),
-            Type::Const => write!(f, "const"),
-            Type::Static => write!(f, "static"),
-            Type::Type => write!(f, "type"),
-            Type::Variant => write!(f, "variant"),
-            Type::Impl => write!(f, "impl"),
-            Type::Use => write!(f, "use"),
-            Type::Trait => write!(f, "trait"),
-            Type::Macro => write!(f, "macro"),
-            _ => write!(f, "?"),
+    // `*` matches within a path segment while `**` matches across segments.
+    pub fn matches_glob(&self, pattern: &str) -> bool {
+        fn matches(pattern: &[char], path: &[char]) -> bool {
+            match pattern.first() {
+                None => path.is_empty(),
+                Some(&'*') if pattern.get(1) == Some(&'*') => {
+                    (0..=path.len()).any(|i| matches(&pattern[2..], &path[i..]))
+                }
+                Some(&'*') => {
+                    let segment = path.iter().position(|&c| c == ':').unwrap_or(path.len());
+                    (0..=segment).any(|i| matches(&pattern[1..], &path[i..]))
+                }
+                Some(c) => path.first() == Some(c) && matches(&pattern[1..], &path[1..]),
+            }
+        }
+        let pattern = pattern.chars().collect::<Vec<_>>();
+        let path = self.full_path().chars().collect::<Vec<_>>();
+        matches(&pattern, &path)
+    }
+
+    // Generic parameters directly follow the name (or the `impl` keyword) so only the first
+    // arg is checked, `fn foo() -> Vec<u8>` isn't generic.
+    pub fn is_generic(&self) -> bool {
+        self.args.first().map(|a| a.starts_with('<')).unwrap_or(false)
+    }
+
+    pub fn args_bracket_balanced(&self) -> bool {
+        fn balanced(arg: &str) -> bool {
+            let mut stack = Vec::new();
+            let mut prev = ' ';
+            for c in arg.chars() {
+                match c {
+                    '(' => stack.push(')'),
+                    '[' => stack.push(']'),
+                    '{' => stack.push('}'),
+                    '<' => stack.push('>'),
+                    // `->` and `=>` aren't closing brackets.
+                    '>' if prev == '-' || prev == '=' => {}
+                    ')' | ']' | '}' | '>' if stack.pop() != Some(c) => return false,
+                    _ => {}
+                }
+                prev = c;
+            }
+            stack.is_empty()
+        }
+        self.args.iter().all(|a| balanced(a))
+    }
+
+    pub fn is_impl_block(&self) -> bool {
+        self.ty == Type::Impl
+    }
+
+    // The implemented type is the one after `for` for trait impls. Impl generics (`<T>`)
+    // are skipped.
+    pub fn impl_target(&self) -> Option<&str> {
+        if !self.is_impl_block() {
+            return None;
+        }
+        let tokens = Some(self.name.as_ref()).into_iter()
+                                             .chain(self.args_iter())
+                                             .filter(|t| !t.is_empty() && !t.starts_with('<'))
+                                             .collect::<Vec<_>>();
+        match tokens.iter().position(|&t| t == "for") {
+            Some(pos) => tokens.get(pos + 1).cloned(),
+            None => tokens.first().cloned(),
+        }
+    }
+
+    // Associated functions count as methods too, see `takes_self` for the receiver.
+    pub fn is_method(&self) -> bool {
+        if self.ty != Type::Fn {
+            return false;
+        }
+        let mut current = self.parent.as_deref();
+        while let Some(p) = current {
+            if p.ty.is_scoped() {
+                return matches!(p.ty, Type::Impl | Type::Trait);
+            }
+            current = p.parent.as_deref();
+        }
+        false
+    }
+
+    // Variants and the items of traits and trait impls can't have a visibility of their own
+    // and are as visible as their parent. `pub(crate)` and such don't count as public.
+    pub fn is_effectively_public(&self) -> bool {
+        let inherits = match self.parent {
+            Some(ref p) => p.ty == Type::Trait || p.impl_kind == Some(ImplKind::Trait),
+            None => false,
+        };
+        let public = self.visibility.as_deref() == Some("pub") ||
+                     self.ty == Type::Variant ||
+                     inherits;
+        public && self.ancestors().into_iter()
+                                  .filter(|a| a.ty == Type::Mod)
+                                  .all(|a| a.visibility.as_deref() == Some("pub"))
+    }
+
+    // Relies on `has_default`, which `build_tree` sets.
+    pub fn is_required_method(&self) -> bool {
+        self.ty == Type::Fn &&
+        !self.has_default &&
+        matches!(self.parent, Some(ref p) if p.ty == Type::Trait)
+    }
+
+    pub fn takes_self(&self) -> bool {
+        if self.ty != Type::Fn {
+            return false;
         }
+        let sig = self.args.join(" ");
+        // The parameters are in the first parentheses which aren't part of the generics.
+        let mut depth = 0usize;
+        let mut prev = ' ';
+        let mut params = None;
+        for (pos, c) in sig.char_indices() {
+            match c {
+                '<' => depth += 1,
+                '>' if prev != '-' => depth = depth.saturating_sub(1),
+                '(' if depth == 0 => {
+                    params = Some(&sig[pos + 1..]);
+                    break;
+                }
+                _ => {}
+            }
+            prev = c;
+        }
+        match params {
+            Some(params) => is_receiver(params.split(&[',', ')'][..]).next().unwrap_or("")),
+            None => false,
+        }
+    }
+
+    // A rough score of how complex the signature is: the number of parameters, plus twice the
+    // number of generic parameters, plus the number of bounds, whether they're inline or in the
+    // `where` clause.
+    pub fn estimate_complexity(&self) -> u32 {
+        let sig = self.args.join(" ");
+        let params_start = top_level_find(&sig, '(').unwrap_or(sig.len());
+        let generics = match top_level_find(&sig[..params_start], '<') {
+            Some(pos) => Type::parse_generics(bracket_content(&sig[pos..])),
+            None => Vec::new(),
+        };
+        let (params, rest) = match sig.get(params_start..) {
+            Some(rest) if !rest.is_empty() => {
+                let content = bracket_content(rest);
+                (split_top_level(content, |c| c == ','), &rest[content.len() + 1..])
+            }
+            _ => (Vec::new(), ""),
+        };
+        let where_clause = rest.find("where ").map_or("", |pos| &rest[pos + 6..]);
+        let predicates = split_top_level(where_clause, |c| c == ',');
+        let bounds = generics.iter().chain(predicates.iter()).map(|p| match top_level_find(p, ':') {
+            Some(pos) => split_top_level(&p[pos + 1..], |c| c == '+').len(),
+            None => 0,
+        }).sum::<usize>();
+        (params.len() + 2 * generics.len() + bounds) as u32
+    }
+
+    // Returns the parameters of a function, without its receiver. Like `takes_self`, they're
+    // read from the first parentheses which aren't part of the generics.
+    pub fn args_without_self(&self) -> Vec<String> {
+        if self.ty != Type::Fn {
+            return Vec::new();
+        }
+        let sig = self.args.join(" ");
+        let mut params = match top_level_find(&sig, '(') {
+            Some(pos) => split_top_level(bracket_content(&sig[pos..]), |c| c == ','),
+            None => Vec::new(),
+        };
+        if params.first().map(|p| is_receiver(p)).unwrap_or(false) {
+            params.remove(0);
+        }
+        params
+    }
+
+    // The path may be in the name or spread over the args, as `parse_item_line` does it.
+    pub fn as_use_reexport(&self) -> Option<(String, Option<String>)> {
+        if self.ty != Type::Use {
+            return None;
+        }
+        let full = Some(self.name.as_ref()).into_iter()
+                                           .chain(self.args_iter())
+                                           .collect::<Vec<_>>()
+                                           .join(" ")
+                                           .replace(":: ", "::");
+        let tokens = full.trim_end_matches(';').split_whitespace().collect::<Vec<_>>();
+        match tokens.iter().rposition(|&t| t == "as") {
+            Some(pos) if pos > 0 && pos + 2 == tokens.len() => {
+                Some((tokens[..pos].join(" "), Some(tokens[pos + 1].to_owned())))
+            }
+            _ => Some((tokens.join(" "), None)),
+        }
+    }
+
+    // The `macro_kind` of macros is deduced from the keyword.
+    pub fn with_raw_keyword(mut self, keyword: &str) -> TypeStruct {
+        if self.ty == Type::Macro {
+            self.macro_kind = MacroKind::from_keyword(keyword);
+        }
+        self.raw_keyword = Some(keyword.to_owned());
+        self
+    }
+
+    pub fn with_doc(mut self, lines: Vec<String>) -> TypeStruct {
+        self.docs = lines;
+        self
+    }
+
+    // Lines already in `docs`, including the ones just merged, are skipped so merging the
+    // same lines again does nothing.
+    pub fn merge_docs_from(&mut self, new_lines: &[String]) {
+        for line in new_lines {
+            if !self.docs.contains(line) {
+                self.docs.push(line.clone());
+            }
+        }
+    }
+
+    // The new module becomes the topmost ancestor of the item.
+    pub fn prepend_module(mut self, module: &str) -> TypeStruct {
+        {
+            let mut current = &mut self.parent;
+            while let Some(ref mut p) = *current {
+                current = &mut p.parent;
+            }
+            *current = Some(Box::new(TypeStruct::new(Type::Mod, module)));
+        }
+        self
+    }
+
+    // The closest ancestor equal to `old` is the one replaced. Everything above it is kept,
+    // unless `new` has parents of its own, in which case they're used instead.
+    pub fn replace_ancestor(&mut self, old: &TypeStruct, mut new: TypeStruct) {
+        let mut current = &mut self.parent;
+        while let Some(ref mut p) = *current {
+            if **p == *old {
+                if new.parent.is_none() {
+                    new.parent = p.parent.take();
+                }
+                **p = new;
+                return;
+            }
+            current = &mut p.parent;
+        }
+    }
+
+    // Runs of `Mod` ancestors are merged into a single one named like `a::b::c`, which keeps
+    // the attributes of the innermost module, except for its visibility: the most restrictive
+    // one of the run is kept, so the module is private if any of them is. `full_path` isn't
+    // affected.
+    pub fn collapse_single_child_mods(mut self) -> TypeStruct {
+        let mut ancestors = Vec::new();
+        let mut next = self.parent.take();
+        while let Some(mut p) = next {
+            next = p.parent.take();
+            ancestors.push(*p);
+        }
+        let mut collapsed: Vec<TypeStruct> = Vec::new();
+        for t in ancestors.into_iter().rev() {
+            match collapsed.last_mut() {
+                Some(last) if last.ty == Type::Mod && t.ty == Type::Mod => {
+                    let name = format!("{}::{}", last.name, t.name);
+                    let visibility = if visibility_rank(&last.visibility) <
+                                        visibility_rank(&t.visibility) {
+                        last.visibility.take()
+                    } else {
+                        t.visibility.clone()
+                    };
+                    *last = TypeStruct { name: Cow::Owned(name), visibility, ..t };
+                }
+                _ => collapsed.push(t),
+            }
+        }
+        for mut t in collapsed {
+            t.parent = self.parent.take();
+            self.parent = Some(Box::new(t));
+        }
+        self
+    }
+
+    // Splits the signature of the item, without its parents, into identifiers, lifetimes and
+    // punctuation. `::`, `->` and `=>` are kept as single tokens.
+    pub fn to_tokens(&self) -> Vec<String> {
+        let signature = Signature(self).to_string();
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let mut tokens = Vec::new();
+        let mut chars = signature.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let mut end = start + c.len_utf8();
+            let lifetime = c == '\'' && chars.peek().map(|&(_, n)| is_ident(n)).unwrap_or(false);
+            if is_ident(c) || lifetime {
+                while let Some(&(pos, n)) = chars.peek() {
+                    if !is_ident(n) {
+                        break;
+                    }
+                    end = pos + n.len_utf8();
+                    chars.next();
+                }
+            } else if let Some(&(pos, n)) = chars.peek() {
+                if matches!((c, n), (':', ':') | ('-', '>') | ('=', '>')) {
+                    end = pos + n.len_utf8();
+                    chars.next();
+                }
+            }
+            tokens.push(signature[start..end].to_owned());
+        }
+        tokens
+    }
+
+    // Headings past level 6 don't exist in markdown so `level` is clamped to `1..=6`.
+    pub fn markdown_header(&self, level: usize) -> String {
+        format!("{} `{}`", "#".repeat(level.clamp(1, 6)), Signature(self))
+    }
+
+    pub fn display_with_theme(&self, theme: &dyn Theme) -> String {
+        Themed(self, theme).to_string()
+    }
+
+    pub fn render_with_docs(&self) -> String {
+        let mut out = String::new();
+        for line in &self.docs {
+            if line.is_empty() {
+                out.push_str("///\n");
+            } else {
+                out.push_str(&format!("/// {}\n", line));
+            }
+        }
+        out.push_str(&self.to_string());
+        out
+    }
+
+    // `use` and `impl` names are checked as paths, impls, closures and `gen` blocks may be
+    // unnamed and bounds can be on lifetimes.
+    pub fn sanitize_name(&self) -> Result<(), NameError> {
+        match self.ty {
+            Type::Impl | Type::Closure | Type::Gen if self.name.is_empty() => Ok(()),
+            Type::Use | Type::Impl => check_path(&self.name),
+            Type::Bound if self.name == "'static" => Ok(()),
+            Type::Bound if self.name.starts_with('\'') => check_ident(&self.name[1..], &self.name),
+            _ => check_ident(&self.name, &self.name),
+        }
+    }
+
+    // Variants, bounds, closures, generators and unknown items can't stand on their own so
+    // they give an empty stub.
+    pub fn to_rust_stub(&self) -> String {
+        let sig = format!("{}{}", self.name, self.args.join(" "));
+        match self.ty {
+            Type::Struct => format!("struct {};", sig),
+            Type::Enum => format!("enum {} {{}}", sig),
+            Type::Trait => format!("trait {} {{}}", sig),
+            Type::Mod if !self.inline => format!("mod {};", self.name),
+            Type::Mod => format!("mod {} {{}}", self.name),
+            Type::Impl => format!("impl {} {{}}", sig.trim()),
+            Type::Fn if self.args.iter().any(|a| a.contains('(')) => {
+                format!("fn {} {{ unimplemented!() }}", sig)
+            }
+            Type::Fn => format!("fn {}() {{ unimplemented!() }}", sig),
+            Type::Const => format!("const {}: () = ();", self.name),
+            Type::Static => format!("static {}: () = ();", self.name),
+            Type::Type => format!("type {} = ();", sig),
+            Type::Use => format!("use {};", self.name),
+            Type::Macro => format!("macro_rules! {} {{ () => {{}}; }}", self.name),
+            Type::Variant | Type::Bound | Type::Closure | Type::Dyn | Type::ImplTrait |
+            Type::Gen | Type::Unknown => String::new(),
+        }
+    }
+}
+
+// Checks whether `param` is `self`, `&self`, `&'a mut self`, `self: Box<Self>`...
+fn is_receiver(param: &str) -> bool {
+    let param = param.trim().trim_end_matches(',');
+    let param = match param.strip_prefix('&') {
+        Some(rest) if rest.trim_start().starts_with('\'') => {
+            let rest = rest.trim_start();
+            rest.find(char::is_whitespace).map_or("", |pos| &rest[pos..])
+        }
+        Some(rest) => rest,
+        None => param,
+    }.trim_start();
+    let param = param.strip_prefix("mut ").unwrap_or(param).trim_start();
+    match param.strip_prefix("self") {
+        Some(rest) => rest.trim_end().is_empty() || rest.trim_start().starts_with(':'),
+        None => false,
+    }
+}
+
+// The strict and reserved keywords of the 2018 edition, which can only be used as names when
+// they're raw. `gen` is left out since it's only reserved from the 2024 edition.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn check_ident(name: &str, full: &str) -> Result<(), NameError> {
+    if KEYWORDS.contains(&name) {
+        return Err(NameError::Keyword(full.to_owned()));
+    }
+    let ident = name.strip_prefix("r#").unwrap_or(name);
+    match ident.chars().next() {
+        None => Err(NameError::Empty),
+        Some(c) if c.is_ascii_digit() => Err(NameError::StartsWithDigit(full.to_owned())),
+        _ => match ident.chars().find(|&c| !c.is_alphanumeric() && c != '_') {
+            Some(c) => Err(NameError::InvalidCharacter { name: full.to_owned(), c }),
+            None => Ok(()),
+        },
+    }
+}
+
+// Paths may start with `::` and their segments may be `self`, `super`, `crate` or `Self`.
+fn check_path(name: &str) -> Result<(), NameError> {
+    let path = name.strip_prefix("::").unwrap_or(name);
+    for segment in path.split("::") {
+        match segment {
+            "*" | "self" | "super" | "crate" | "Self" => {}
+            segment => check_ident(segment, name)?,
+        }
+    }
+    Ok(())
+}
+
+// The lower, the more restrictive. `pub(in path)` is ranked below `pub(super)` though it may
+// be as visible.
+fn visibility_rank(visibility: &Option<String>) -> u8 {
+    match visibility.as_deref() {
+        None | Some("pub(self)") => 0,
+        Some("pub") => 4,
+        Some("pub(crate)") => 3,
+        Some("pub(super)") => 2,
+        Some(_) => 1,
+    }
+}
+
+pub fn depth_histogram(items: &[TypeStruct]) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for item in items {
+        *histogram.entry(item.get_depth(false)).or_insert(0) += 1;
+    }
+    histogram
+}
+
+// `docs`, `span` and `has_default` aren't part of the item's signature so they're ignored
+// here, as well as in `Hash`.
+impl PartialEq for TypeStruct {
+    fn eq(&self, other: &TypeStruct) -> bool {
+        self.ty == other.ty &&
+        self.name == other.name &&
+        self.args == other.args &&
+        self.inline == other.inline &&
+        self.raw_keyword == other.raw_keyword &&
+        self.variant_count == other.variant_count &&
+        self.impl_kind == other.impl_kind &&
+        self.type_annotation == other.type_annotation &&
+        self.discriminant == other.discriminant &&
+        self.visibility == other.visibility &&
+        self.macro_kind == other.macro_kind &&
+        self.value == other.value &&
+        self.parent == other.parent
+    }
+}
+
+impl Eq for TypeStruct {}
+
+impl Hash for TypeStruct {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ty.hash(state);
+        self.name.hash(state);
+        self.args.hash(state);
+        self.inline.hash(state);
+        self.raw_keyword.hash(state);
+        self.variant_count.hash(state);
+        self.impl_kind.hash(state);
+        self.type_annotation.hash(state);
+        self.discriminant.hash(state);
+        self.visibility.hash(state);
+        self.macro_kind.hash(state);
+        self.value.hash(state);
+        self.parent.hash(state);
+    }
+}
+
+impl Clone for TypeStruct {
+    fn clone(&self) -> TypeStruct {
+        TypeStruct {
+            ty: self.ty,
+            name: self.name.clone(),
+            args: self.args.clone(),
+            parent: self.parent.as_ref().map(|p| Box::new(p.deref().clone())),
+            inline: self.inline,
+            docs: self.docs.clone(),
+            raw_keyword: self.raw_keyword.clone(),
+            variant_count: self.variant_count,
+            impl_kind: self.impl_kind,
+            type_annotation: self.type_annotation.clone(),
+            discriminant: self.discriminant.clone(),
+            visibility: self.visibility.clone(),
+            span: self.span,
+            has_default: self.has_default,
+            macro_kind: self.macro_kind,
+            value: self.value.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &TypeStruct) {
+        self.ty = source.ty;
+        self.name = source.name.clone();
+        self.args = source.args.clone();
+        self.parent = source.parent.as_ref().map(|p| Box::new(p.deref().clone()));
+        self.inline = source.inline;
+        self.docs = source.docs.clone();
+        self.raw_keyword = source.raw_keyword.clone();
+        self.variant_count = source.variant_count;
+        self.impl_kind = source.impl_kind;
+        self.type_annotation = source.type_annotation.clone();
+        self.discriminant = source.discriminant.clone();
+        self.visibility = source.visibility.clone();
+        self.span = source.span;
+        self.has_default = source.has_default;
+        self.macro_kind = source.macro_kind;
+        self.value = source.value.clone();
+    }
+}
+
+impl Extend<String> for TypeStruct {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, args: I) {
+        self.args.extend(args);
+    }
+}
+
+impl<'a> Extend<&'a str> for TypeStruct {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, args: I) {
+        self.args.extend(args.into_iter().map(|arg| arg.to_owned()));
+    }
+}
+
+// Same as `Display`, except that macro parents are kept.
+impl Debug for TypeStruct {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        sub_call(f, self, false, true, &PlainTheme)
+    }
+}
+
+// Styles the parts of a rendered item, see `TypeStruct::display_with_theme`.
+pub trait Theme {
+    fn keyword(&self, ty: Type) -> String;
+    fn name(&self, name: &str) -> String;
+    // Written between the levels of the parent chain.
+    fn separator(&self) -> &str;
+
+    // Used in place of `keyword` for `Type::Unknown` items with a `raw_keyword` and for
+    // `macro_rules!`.
+    fn raw_keyword(&self, raw: &str) -> String {
+        raw.to_owned()
+    }
+}
+
+// Renders items the same way as `Display` does.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainTheme;
+
+impl Theme for PlainTheme {
+    fn keyword(&self, ty: Type) -> String {
+        ty.to_string()
+    }
+
+    fn name(&self, name: &str) -> String {
+        name.to_owned()
+    }
+
+    fn separator(&self) -> &str {
+        "§"
+    }
+}
+
+// Closure args are its parameters, followed by the return type if the last one starts
+// with `->`.
+fn show_closure(f: &mut Formatter, t: &TypeStruct, theme: &dyn Theme) -> Result<(), Error> {
+    let (params, ret) = match t.args.last() {
+        Some(last) if last.starts_with("->") => (&t.args[..t.args.len() - 1], Some(last)),
+        _ => (&t.args[..], None),
+    };
+    if !t.name.is_empty() {
+        write!(f, "{}: ", theme.name(&t.name))?;
+    }
+    write!(f, "|{}|", params.join(", "))?;
+    match ret {
+        Some(ret) => write!(f, " {}", ret),
+        None => Ok(()),
+    }
+}
+
+fn show(f: &mut Formatter, t: &TypeStruct, is_parent: bool,
+        theme: &dyn Theme) -> Result<(), Error> {
+    match t.ty {
+        Type::Bound => write!(f, "{}: {}", theme.name(&t.name), t.args.join(" + "))?,
+        Type::Closure => show_closure(f, t, theme)?,
+        _ => {
+            match (t.ty, t.raw_keyword.as_ref()) {
+                (Type::Unknown, Some(raw)) => write!(f, "{} ", theme.raw_keyword(raw))?,
+                // Rather than a `?` placeholder, unclassified items get no keyword at all.
+                (Type::Unknown, None) => {}
+                (Type::Macro, _) if t.macro_kind == Some(MacroKind::Rules) => {
+                    write!(f, "{} ", theme.raw_keyword(MacroKind::Rules.as_str()))?
+                }
+                (ty, _) => write!(f, "{} ", theme.keyword(ty))?,
+            }
+            write!(f, "{}{}", theme.name(&t.name), t.args.join(" "))?;
+            match (t.ty, t.type_annotation.as_ref()) {
+                (Type::Const, Some(ann)) | (Type::Static, Some(ann)) => write!(f, ": {}", ann)?,
+                (Type::Type, Some(ann)) => write!(f, " = {}", ann)?,
+                _ => {}
+            }
+            match (t.ty, t.value.as_ref()) {
+                (Type::Const, Some(value)) | (Type::Static, Some(value)) => {
+                    write!(f, " = {}", value)?
+                }
+                _ => {}
+            }
+            if let (Type::Variant, Some(d)) = (t.ty, t.discriminant.as_ref()) {
+                write!(f, " = {}", d)?;
+            }
+        }
+    }
+    if is_parent {
+        write!(f, "{}", theme.separator())
+    } else if t.ty == Type::Mod && !t.inline {
+        write!(f, ";")
+    } else {
+        Ok(())
+    }
+}
+
+// Renders the item alone, without its parents.
+struct Signature<'a>(&'a TypeStruct);
+
+impl<'a> Display for Signature<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        show(f, self.0, false, &PlainTheme)
+    }
+}
+
+// Macro parents are skipped unless `keep_macros` is set.
+fn sub_call(f: &mut Formatter, t: &TypeStruct, is_parent: bool, keep_macros: bool,
+            theme: &dyn Theme) -> Result<(), Error> {
+    if t.ty == Type::Macro && is_parent && !keep_macros {
+        match t.parent {
+            Some(ref p) => sub_call(f, p.borrow(), true, keep_macros, theme),
+            _ => Ok(()),
+        }
+    } else {
+        match t.parent {
+            Some(ref p) => {
+                sub_call(f, p.borrow(), true, keep_macros, theme)?;
+                show(f, t, is_parent, theme)
+            },
+            _ => show(f, t, is_parent, theme),
+        }
+    }
+}
+
+struct Themed<'a>(&'a TypeStruct, &'a dyn Theme);
+
+impl<'a> Display for Themed<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        sub_call(f, self.0, false, false, self.1)
+    }
+}
+
+// `{:#}` renders each level of the parent chain on its own line, indented by its depth.
+impl Display for TypeStruct {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        if !f.alternate() {
+            return sub_call(f, self, false, false, &PlainTheme);
+        }
+        let chain = self.ancestors().into_iter().filter(|t| t.ty != Type::Macro);
+        for (depth, t) in chain.chain(Some(self)).enumerate() {
+            if depth > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", "    ".repeat(depth))?;
+            show(f, t, false, &PlainTheme)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Type {
+    Struct,
+    Mod,
+    Enum,
+    Fn,
+    Const,
+    Static,
+    Type,
+    Variant,
+    Impl,
+    Use,
+    Macro,
+    Trait,
+    Bound,
+    Closure,
+    Dyn,
+    ImplTrait,
+    /// A generator: a `gen` block or function.
+    Gen,
+    Unknown,
+}
+
+// Additional keywords to recognize on top of the ones `Type::from` knows about.
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+    keywords: HashMap<String, Type>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> TypeRegistry {
+        TypeRegistry {
+            keywords: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, keyword: &str, ty: Type) {
+        self.keywords.insert(keyword.to_owned(), ty);
+    }
+
+    pub fn get(&self, keyword: &str) -> Option<Type> {
+        self.keywords.get(keyword).cloned()
+    }
+}
+
+const MACRO_ALIASES: &[&str] = &["macro", "macro_rules", "macro_rules!"];
+
+const ALL_TYPES: &[Type] = &[
+    Type::Struct,
+    Type::Mod,
+    Type::Enum,
+    Type::Fn,
+    Type::Const,
+    Type::Static,
+    Type::Type,
+    Type::Variant,
+    Type::Impl,
+    Type::Use,
+    Type::Macro,
+    Type::Trait,
+    Type::Bound,
+    Type::Closure,
+    Type::Dyn,
+    Type::ImplTrait,
+    Type::Gen,
+    Type::Unknown,
+];
+
+impl Type {
+    pub fn all() -> &'static [Type] {
+        ALL_TYPES
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Type::Struct => "struct",
+            Type::Mod => "mod",
+            Type::Enum => "enum",
+            Type::Fn => "fn",
+            Type::Const => "const",
+            Type::Static => "static",
+            Type::Type => "type",
+            Type::Variant => "variant",
+            Type::Impl => "impl",
+            Type::Use => "use",
+            Type::Trait => "trait",
+            Type::Macro => "macro",
+            Type::Bound => "bound",
+            Type::Closure => "closure",
+            Type::Dyn => "dyn",
+            Type::ImplTrait => "impl_trait",
+            Type::Gen => "gen",
+            Type::Unknown => "?",
+        }
+    }
+
+    pub fn macro_aliases() -> &'static [&'static str] {
+        MACRO_ALIASES
+    }
+
+    // Checks that `Type::from` gives back every type from its `as_str` keyword, returning the
+    // first one it doesn't. Only types starting an item are checked: variants, bounds,
+    // closures, `dyn` and `impl` types and unknown items don't start with a keyword of their own
+    // in the source.
+    pub fn check_roundtrip() -> Result<(), Type> {
+        let not_items = [Type::Variant, Type::Bound, Type::Closure, Type::Dyn, Type::ImplTrait,
+                         Type::Unknown];
+        match Type::all().iter()
+                         .filter(|t| !not_items.contains(t))
+                         .find(|&&t| Type::from(t.as_str()) != t) {
+            Some(&t) => Err(t),
+            None => Ok(()),
+        }
+    }
+
+    // Keywords which can't start an item, such as the `for` of `impl Trait for Type`, are
+    // `Type::Unknown` so they aren't taken for variant names. Raw identifiers like `r#struct`
+    // are names, never keywords.
+    pub fn from(s: &str) -> Type {
+        match s {
+            s if s.starts_with("r#") => Type::Variant,
+            "struct" => Type::Struct,
+            "mod" => Type::Mod,
+            "enum" => Type::Enum,
+            "fn" => Type::Fn,
+            "const" => Type::Const,
+            "static" => Type::Static,
+            "type" => Type::Type,
+            "impl" => Type::Impl,
+            "use" => Type::Use,
+            "trait" => Type::Trait,
+            "gen" => Type::Gen,
+            s if MACRO_ALIASES.contains(&s) => Type::Macro,
+            "where" | "for" | "as" | "in" | "dyn" => Type::Unknown,
+            _ => Type::Variant,
+        }
+    }
+
+    // `extern crate` is classified as `Type::Use` since it imports a crate.
+    pub fn from_keywords(tokens: &[&str]) -> Type {
+        find_item_keyword(tokens).map_or(Type::Unknown, |(_, ty)| ty)
+    }
+
+    // In type position, `impl` is followed by a trait rather than starting an item.
+    pub fn from_type_expr(expr: &str) -> Type {
+        let mut tokens = expr.split_whitespace();
+        match (tokens.next(), tokens.next()) {
+            (Some("dyn"), Some(_)) => Type::Dyn,
+            (Some("impl"), Some(_)) => Type::ImplTrait,
+            (Some(token), _) => Type::from(token),
+            (None, _) => Type::Unknown,
+        }
+    }
+
+    // Kinds without a matching `Type`, such as `"primitive"` or `"struct_field"`, give `None`.
+    pub fn from_doc_kind(kind: &str) -> Option<Type> {
+        Some(match kind {
+            "module" => Type::Mod,
+            "extern_crate" | "import" => Type::Use,
+            "struct" => Type::Struct,
+            "enum" => Type::Enum,
+            "variant" => Type::Variant,
+            "function" => Type::Fn,
+            "type_alias" | "typedef" | "assoc_type" => Type::Type,
+            "constant" | "assoc_const" => Type::Const,
+            "static" => Type::Static,
+            "trait" => Type::Trait,
+            "impl" => Type::Impl,
+            "macro" | "proc_attribute" | "proc_derive" => Type::Macro,
+            _ => return None,
+        })
+    }
+
+    pub fn category(&self) -> Category {
+        match *self {
+            Type::Mod | Type::Trait | Type::Impl => Category::Container,
+            Type::Fn | Type::Closure | Type::Gen | Type::Macro => Category::Callable,
+            Type::Struct | Type::Enum | Type::Variant | Type::Type | Type::Const |
+            Type::Static => Category::Data,
+            Type::Use => Category::Reexport,
+            Type::Bound | Type::Dyn | Type::ImplTrait | Type::Unknown => Category::Synthetic,
+        }
+    }
+
+    // Whether the item defines a name, as opposed to `use` which only refers to one defined
+    // elsewhere.
+    pub fn is_type_definition(&self) -> bool {
+        matches!(*self, Type::Struct | Type::Enum | Type::Trait | Type::Fn | Type::Const |
+                        Type::Static | Type::Type | Type::Mod)
+    }
+
+    pub fn is_scoped(&self) -> bool {
+        matches!(*self, Type::Struct | Type::Mod | Type::Enum | Type::Fn | Type::Impl |
+                        Type::Macro | Type::Trait | Type::Gen)
+    }
+
+    pub fn render_opening(&self, name_and_args: &str) -> String {
+        match *self {
+            Type::Macro => format!("macro_rules! {} {{", name_and_args),
+            Type::Impl if name_and_args.is_empty() => "impl {".to_owned(),
+            t if t.is_scoped() => format!("{} {} {{", t, name_and_args),
+            Type::Const | Type::Static | Type::Type | Type::Use => {
+                format!("{} {};", self, name_and_args)
+            }
+            Type::Variant => format!("{},", name_and_args),
+            _ => name_and_args.to_owned(),
+        }
+    }
+
+    pub fn render_closing(&self) -> String {
+        if self.is_scoped() {
+            "}".to_owned()
+        } else {
+            String::new()
+        }
+    }
+
+    // Identifies the type in `TypeStruct::checksum_stable`. These values must never change,
+    // new types get new ones.
+    fn checksum_tag(&self) -> u8 {
+        match *self {
+            Type::Struct => 1,
+            Type::Mod => 2,
+            Type::Enum => 3,
+            Type::Fn => 4,
+            Type::Const => 5,
+            Type::Static => 6,
+            Type::Type => 7,
+            Type::Variant => 8,
+            Type::Impl => 9,
+            Type::Use => 10,
+            Type::Macro => 11,
+            Type::Trait => 12,
+            Type::Bound => 13,
+            Type::Closure => 14,
+            Type::Dyn => 15,
+            Type::ImplTrait => 16,
+            Type::Gen => 17,
+            Type::Unknown => 18,
+        }
+    }
+
+    // Short (at most two letters) and unique form of the keyword, for compact displays.
+    pub fn abbreviation(&self) -> &'static str {
+        match *self {
+            Type::Struct => "st",
+            Type::Mod => "md",
+            Type::Enum => "en",
+            Type::Fn => "fn",
+            Type::Const => "ct",
+            Type::Static => "sc",
+            Type::Type => "ty",
+            Type::Variant => "va",
+            Type::Impl => "im",
+            Type::Use => "us",
+            Type::Macro => "ma",
+            Type::Trait => "tr",
+            Type::Bound => "bd",
+            Type::Closure => "cl",
+            Type::Dyn => "dy",
+            Type::ImplTrait => "it",
+            Type::Gen => "ge",
+            Type::Unknown => "?",
+        }
+    }
+
+    // SGR foreground code, to be used as `\x1b[<code>m`: types are cyan, functions green,
+    // traits magenta, modules blue, values yellow, impls and macros red. Unknown items keep
+    // the terminal's default color.
+    pub fn ansi_color(&self) -> &'static str {
+        match *self {
+            Type::Struct | Type::Enum | Type::Type | Type::Variant => "36",
+            Type::Fn | Type::Closure | Type::Gen => "32",
+            Type::Trait | Type::Bound | Type::Dyn | Type::ImplTrait => "35",
+            Type::Mod | Type::Use => "34",
+            Type::Const | Type::Static => "33",
+            Type::Impl | Type::Macro => "31",
+            Type::Unknown => "39",
+        }
+    }
+
+    // Rank used to group items when sorting, unlike `Ord` which follows declaration order:
+    //
+    // 0. modules
+    // 1. types (structs, enums and type aliases)
+    // 2. traits
+    // 3. impls
+    // 4. functions and generators
+    // 5. constants and statics
+    // 6. macros
+    // 7. imports
+    // 8. everything else (variants, bounds, closures, ...)
+    pub fn sort_rank(&self) -> u8 {
+        match *self {
+            Type::Mod => 0,
+            Type::Struct | Type::Enum | Type::Type => 1,
+            Type::Trait => 2,
+            Type::Impl => 3,
+            Type::Fn | Type::Gen => 4,
+            Type::Const | Type::Static => 5,
+            Type::Macro => 6,
+            Type::Use => 7,
+            Type::Variant | Type::Bound | Type::Closure | Type::Dyn | Type::ImplTrait |
+            Type::Unknown => 8,
+        }
+    }
+
+    pub fn from_registry(s: &str, registry: &TypeRegistry) -> Type {
+        registry.get(s).unwrap_or_else(|| Type::from(s))
+    }
+
+    // A `::` right after the keyword is skipped along with the whitespace. If `line` doesn't
+    // start with a keyword, it's returned whole with `Type::Unknown`.
+    pub fn split_keyword(line: &str) -> (Type, &str) {
+        let line = line.trim_start();
+        let keyword_len = line.find(|c: char| !c.is_ascii_lowercase() && c != '_' && c != '!')
+                              .unwrap_or(line.len());
+        match Type::from(&line[..keyword_len]) {
+            Type::Variant | Type::Unknown => (Type::Unknown, line),
+            ty => {
+                let rest = &line[keyword_len..];
+                (ty, rest.strip_prefix("::").unwrap_or(rest).trim_start())
+            }
+        }
+    }
+
+    // `raw` may or may not be surrounded by its `<>`.
+    pub fn parse_generics(raw: &str) -> Vec<String> {
+        let raw = raw.trim();
+        let inner = match raw.strip_prefix('<').and_then(|r| r.strip_suffix('>')) {
+            Some(inner) => inner,
+            None => raw,
+        };
+        split_top_level(inner, |c| c == ',')
+    }
+
+    // Qualifiers like `const`, `async` or `unsafe` are skipped the same way `from_keywords`
+    // does it.
+    pub fn parse_item_line(line: &str) -> Option<(Type, String, Vec<String>)> {
+        let line = strip_visibility(line.trim());
+        let line = line.trim_end_matches(|c: char| c == '{' || c == ';' || c.is_whitespace());
+        // Each leading word along with what follows it.
+        let mut words = Vec::new();
+        let mut rest = line;
+        loop {
+            let len = if let Some(quoted) = rest.strip_prefix('"') {
+                quoted.find('"').map_or(rest.len(), |end| end + 2)
+            } else {
+                rest.find(|c: char| !c.is_ascii_lowercase() && c != '_' && c != '!')
+                    .unwrap_or(rest.len())
+            };
+            if len == 0 {
+                break;
+            }
+            words.push((&rest[..len], &rest[len..]));
+            match rest[len..].strip_prefix(char::is_whitespace) {
+                Some(next) => rest = next.trim_start(),
+                None => break,
+            }
+        }
+        let tokens = words.iter().map(|&(word, _)| word).collect::<Vec<_>>();
+        let (pos, ty) = find_item_keyword(&tokens)?;
+        let rest = words[pos].1.trim_start();
+        // `use` items are named after their whole path.
+        let name_len = rest.find(|c: char| {
+            !c.is_alphanumeric() && c != '_' && !(ty == Type::Use && c == ':')
+        }).unwrap_or(rest.len());
+        Some((ty, rest[..name_len].to_owned(),
+              split_top_level(&rest[name_len..], |c| c.is_whitespace())))
+    }
+}
+
+// Returns the position of the token ending the item keyword, skipping the visibility and
+// qualifiers, along with the type of the item. Gives `None` if a token which is neither is
+// found first.
+fn find_item_keyword(tokens: &[&str]) -> Option<(usize, Type)> {
+    for (pos, token) in tokens.iter().enumerate() {
+        let next = tokens.get(pos + 1).cloned();
+        match *token {
+            "pub" | "async" | "unsafe" | "default" => {}
+            t if t.starts_with("pub(") || t.starts_with('"') => {}
+            "extern" if next == Some("crate") => return Some((pos + 1, Type::Use)),
+            "extern" => {}
+            "const" if matches!(next, Some("fn") | Some("unsafe") | Some("async") |
+                                      Some("extern")) => {}
+            t => {
+                return match Type::from(t) {
+                    Type::Variant | Type::Unknown => None,
+                    ty => Some((pos, ty)),
+                };
+            }
+        }
+    }
+    None
+}
+
+fn strip_visibility(line: &str) -> &str {
+    if !line.starts_with("pub") {
+        return line;
+    }
+    let rest = &line[3..];
+    if rest.starts_with('(') {
+        match rest.find(')') {
+            Some(end) => rest[end + 1..].trim_start(),
+            None => line,
+        }
+    } else if rest.starts_with(char::is_whitespace) {
+        rest.trim_start()
+    } else {
+        line
+    }
+}
+
+// Returns the position of the first `target` which isn't inside brackets, or which opens the
+// first bracket.
+fn top_level_find(s: &str, target: char) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut prev = ' ';
+    for (pos, c) in s.char_indices() {
+        if depth == 0 && c == target {
+            return Some(pos);
+        }
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            '>' if prev == '-' || prev == '=' => {}
+            ')' | ']' | '}' | '>' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+// `s` starts with an opening bracket, returns what's inside of it and its closing bracket.
+fn bracket_content(s: &str) -> &str {
+    let mut depth = 0usize;
+    let mut prev = ' ';
+    for (pos, c) in s.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            '>' if prev == '-' || prev == '=' => {}
+            ')' | ']' | '}' | '>' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return &s[1..pos];
+                }
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    &s[1..]
+}
+
+// Splits `s` on the characters matching `is_sep` which aren't inside brackets. Empty parts
+// are skipped.
+fn split_top_level<F: Fn(char) -> bool>(s: &str, is_sep: F) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut prev = ' ';
+    for c in s.chars() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            // `->` and `=>` aren't closing brackets.
+            '>' if prev == '-' || prev == '=' => {}
+            ')' | ']' | '}' | '>' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if depth == 0 && is_sep(c) {
+            if !current.trim().is_empty() {
+                parts.push(current.trim().to_owned());
+            }
+            current.clear();
+        } else {
+            current.push(c);
+        }
+        prev = c;
+    }
+    if !current.trim().is_empty() {
+        parts.push(current.trim().to_owned());
+    }
+    parts
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Type::ImplTrait => write!(f, "impl"),
+            ty => write!(f, "{}", ty.as_str()),
+        }
+    }
+}
+
+// Compares with the keyword given by `as_str`.
+impl PartialEq<Type> for str {
+    fn eq(&self, other: &Type) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Type> for &str {
+    fn eq(&self, other: &Type) -> bool {
+        *self == other.as_str()
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use self::serde_impls::serialize_fields;
+
+// `Type` is (de)serialized as its `as_str` keyword, so `Type::Struct` becomes `"struct"`.
+// `TypeStruct` is serialized as the object `TypeStruct::json_schema` describes.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use serde::de::{self, Deserialize, Deserializer, Visitor};
+    use serde::ser::{Serialize, SerializeStruct, Serializer};
+    use std::fmt::{self, Formatter};
+    use super::{ImplKind, MacroKind, Span, Type, TypeStruct};
+
+    // Not exported, `events_to_tree_json` uses it too. Writes every field but `parent`.
+    pub fn serialize_fields<S: SerializeStruct>(t: &TypeStruct,
+                                                s: &mut S) -> Result<(), S::Error> {
+        s.serialize_field("ty", &t.ty)?;
+        s.serialize_field("name", &t.name)?;
+        s.serialize_field("args", &t.args)?;
+        s.serialize_field("inline", &t.inline)?;
+        s.serialize_field("docs", &t.docs)?;
+        s.serialize_field("raw_keyword", &t.raw_keyword)?;
+        s.serialize_field("variant_count", &t.variant_count)?;
+        s.serialize_field("impl_kind", &t.impl_kind)?;
+        s.serialize_field("type_annotation", &t.type_annotation)?;
+        s.serialize_field("discriminant", &t.discriminant)?;
+        s.serialize_field("visibility", &t.visibility)?;
+        s.serialize_field("span", &t.span)?;
+        s.serialize_field("has_default", &t.has_default)?;
+        s.serialize_field("macro_kind", &t.macro_kind)?;
+        s.serialize_field("value", &t.value)?;
+        Ok(())
+    }
+
+    impl Serialize for TypeStruct {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct("TypeStruct", 16)?;
+            serialize_fields(self, &mut s)?;
+            s.serialize_field("parent", &self.parent)?;
+            s.end()
+        }
+    }
+
+    impl Serialize for Span {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct("Span", 2)?;
+            s.serialize_field("start", &self.start)?;
+            s.serialize_field("end", &self.end)?;
+            s.end()
+        }
+    }
+
+    impl Serialize for ImplKind {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    impl Serialize for MacroKind {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    impl Serialize for Type {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    struct TypeVisitor;
+
+    impl<'de> Visitor<'de> for TypeVisitor {
+        type Value = Type;
+
+        fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "an item keyword")
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Type, E> {
+            match Type::all().iter().find(|t| t.as_str() == s) {
+                Some(&t) => Ok(t),
+                None if Type::macro_aliases().contains(&s) => Ok(Type::Macro),
+                None => Err(E::invalid_value(de::Unexpected::Str(s), &self)),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Type {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Type, D::Error> {
+            deserializer.deserialize_str(TypeVisitor)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_json::{self, Value};
+        use super::super::{Span, Type, TypeStruct};
+
+        #[test]
+        fn json_schema_matches_serialization() {
+            let schema: Value = serde_json::from_str(TypeStruct::json_schema()).unwrap();
+            let mut t = TypeStruct::new(Type::Fn, "f");
+            t.span = Some(Span { start: 1, end: 2 });
+            t.parent = Some(Box::new(TypeStruct::new(Type::Mod, "m")));
+            let value = serde_json::to_value(&t).unwrap();
+            let mut keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+            let mut properties = schema["properties"].as_object().unwrap().keys()
+                                                     .collect::<Vec<_>>();
+            let mut required = schema["required"].as_array().unwrap().iter()
+                                                 .map(|r| r.as_str().unwrap())
+                                                 .collect::<Vec<_>>();
+            keys.sort();
+            properties.sort();
+            required.sort();
+            assert_eq!(keys, properties);
+            assert_eq!(keys, required);
+            assert_eq!(value["ty"], "fn");
+            assert_eq!(value["parent"]["name"], "m");
+            assert_eq!(value["parent"]["parent"], Value::Null);
+            assert_eq!(value["span"]["end"], 2);
+            let types = schema["properties"]["ty"]["enum"].as_array().unwrap();
+            assert!(Type::all().iter().all(|t| types.iter().any(|k| k == t.as_str())));
+        }
+
+        #[test]
+        fn type_as_string() {
+            assert_eq!(serde_json::to_string(&Type::Struct).unwrap(), "\"struct\"");
+            assert_eq!(serde_json::from_str::<Type>("\"struct\"").unwrap(), Type::Struct);
+            assert_eq!(serde_json::from_str::<Type>("\"macro_rules!\"").unwrap(), Type::Macro);
+            for &t in Type::all() {
+                let json = serde_json::to_string(&t).unwrap();
+                assert_eq!(serde_json::from_str::<Type>(&json).unwrap(), t);
+            }
+            assert!(serde_json::from_str::<Type>("\"yeet\"").is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(ty: Type, name: &str, args: &[&str]) -> Option<(Type, String, Vec<String>)> {
+        Some((ty, name.to_owned(), args.iter().map(|a| a.to_string()).collect()))
+    }
+
+    #[test]
+    fn parse_item_line() {
+        assert_eq!(Type::parse_item_line("pub struct Foo {"), parsed(Type::Struct, "Foo", &[]));
+        assert_eq!(Type::parse_item_line("fn foo<T: Clone>(a: T) -> T {"),
+                   parsed(Type::Fn, "foo", &["<T: Clone>(a: T)", "->", "T"]));
+        assert_eq!(Type::parse_item_line("pub(crate) enum E"), parsed(Type::Enum, "E", &[]));
+        assert_eq!(Type::parse_item_line("const fn foo()"), parsed(Type::Fn, "foo", &["()"]));
+        assert_eq!(Type::parse_item_line("pub unsafe fn foo(a: i32)"),
+                   parsed(Type::Fn, "foo", &["(a: i32)"]));
+        assert_eq!(Type::parse_item_line("pub async fn foo()"), parsed(Type::Fn, "foo", &["()"]));
+        assert_eq!(Type::parse_item_line("pub extern \"C\" fn foo()"),
+                   parsed(Type::Fn, "foo", &["()"]));
+        assert_eq!(Type::parse_item_line("const MAX: u32 = 1;"),
+                   parsed(Type::Const, "MAX", &[":", "u32", "=", "1"]));
+        assert_eq!(Type::parse_item_line("unsafe impl<T> Send for Foo<T>"),
+                   parsed(Type::Impl, "", &["<T>", "Send", "for", "Foo<T>"]));
+        assert_eq!(Type::parse_item_line("extern crate foo;"), parsed(Type::Use, "foo", &[]));
+        assert_eq!(Type::parse_item_line("let x = 1;"), None);
+    }
+
+    #[test]
+    fn from_keywords() {
+        assert_eq!(Type::from_keywords(&["pub", "async", "fn"]), Type::Fn);
+        assert_eq!(Type::from_keywords(&["pub", "struct"]), Type::Struct);
+        assert_eq!(Type::from_keywords(&["extern", "crate"]), Type::Use);
+        assert_eq!(Type::from_keywords(&["pub", "let"]), Type::Unknown);
+    }
+
+    #[test]
+    fn const_value() {
+        let mut max = TypeStruct::new(Type::Const, "MAX");
+        max.type_annotation = Some("u32".to_owned());
+        max.value = Some("100".to_owned());
+        assert_eq!(max.to_string(), "const MAX: u32 = 100");
+        let mut other = max.clone();
+        assert_eq!(max, other);
+        other.value = Some("1".to_owned());
+        assert_ne!(max, other);
+    }
+
+    #[test]
+    fn file_module() {
+        let mut m = TypeStruct::new(Type::Mod, "m");
+        assert_eq!(m.to_string(), "mod m");
+        m.inline = false;
+        assert_eq!(m.to_string(), "mod m;");
+    }
+
+    #[test]
+    fn checksum_stable_golden() {
+        assert_eq!(TypeStruct::new(Type::Fn, "f").checksum_stable(), 0xb1e3_4d85_0268_ee67);
+        let mut max = TypeStruct::new(Type::Const, "MAX");
+        max.type_annotation = Some("u32".to_owned());
+        max.value = Some("100".to_owned());
+        max.visibility = Some("pub".to_owned());
+        max.parent = Some(Box::new(TypeStruct::new(Type::Mod, "m")));
+        assert_eq!(max.checksum_stable(), 0x2c9c_51f3_5a3b_6709);
+        // Fields ignored by `==` don't change the checksum.
+        let mut documented = max.clone();
+        documented.docs.push("doc".to_owned());
+        documented.span = Some(Span { start: 0, end: 1 });
+        assert_eq!(documented.checksum_stable(), max.checksum_stable());
+        // Optional fields are tagged, a set empty value isn't the same as no value.
+        let mut a = TypeStruct::new(Type::Macro, "m");
+        a.macro_kind = Some(MacroKind::Rules);
+        let mut b = TypeStruct::new(Type::Macro, "m");
+        b.value = Some(String::new());
+        assert_ne!(a.checksum_stable(), b.checksum_stable());
+        assert_ne!(b.checksum_stable(), TypeStruct::new(Type::Macro, "m").checksum_stable());
+    }
+
+    fn path(names: &[&str]) -> TypeStruct {
+        let mut parent = None;
+        for name in names {
+            let mut t = TypeStruct::new(Type::Mod, name);
+            t.parent = parent.map(Box::new);
+            parent = Some(t);
+        }
+        parent.unwrap()
+    }
+
+    #[test]
+    fn replace_ancestor() {
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.parent = Some(Box::new(path(&["a", "b"])));
+        f.replace_ancestor(&path(&["a", "b"]), TypeStruct::new(Type::Mod, "x"));
+        assert_eq!(f.full_path(), "a::x::f");
+        f.replace_ancestor(&path(&["a", "x"]), path(&["y", "z"]));
+        assert_eq!(f.full_path(), "y::z::f");
+        // Nothing is done if `old` isn't an ancestor.
+        f.replace_ancestor(&path(&["a"]), TypeStruct::new(Type::Mod, "w"));
+        assert_eq!(f.full_path(), "y::z::f");
+    }
+
+    #[test]
+    fn eq_ignore_name_case() {
+        let upper = TypeStruct::new(Type::Const, "Foo");
+        let mut lower = TypeStruct::new(Type::Const, "foo");
+        assert!(upper.eq_ignore_name_case(&lower));
+        assert!(upper != lower);
+        lower.value = Some("2".to_owned());
+        assert!(!upper.eq_ignore_name_case(&lower));
+        let mut child = TypeStruct::new(Type::Fn, "f");
+        child.parent = Some(Box::new(upper));
+        let mut other = TypeStruct::new(Type::Fn, "F");
+        other.parent = Some(Box::new(TypeStruct::new(Type::Const, "foo")));
+        assert!(!child.eq_ignore_name_case(&other));
+    }
+
+    #[test]
+    fn unknown_has_no_placeholder() {
+        let mut unknown = TypeStruct::new(Type::Unknown, "x");
+        unknown.parent = Some(Box::new(TypeStruct::new(Type::Mod, "m")));
+        assert_eq!(unknown.to_string(), "mod m§x");
+        assert_eq!(format!("{:?}", unknown), "mod m§x");
+        let unknown = unknown.with_raw_keyword("union");
+        assert_eq!(unknown.to_string(), "mod m§union x");
+        assert_eq!(format!("{:?}", unknown), "mod m§union x");
+    }
+
+    struct Backticks;
+
+    impl Theme for Backticks {
+        fn keyword(&self, ty: Type) -> String {
+            ty.to_string()
+        }
+
+        fn name(&self, name: &str) -> String {
+            format!("`{}`", name)
+        }
+
+        fn separator(&self) -> &str {
+            "::"
+        }
+    }
+
+    #[test]
+    fn display_with_theme() {
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.args.push("(a: u8)".to_owned());
+        f.parent = Some(Box::new(TypeStruct::new(Type::Mod, "m")));
+        assert_eq!(f.display_with_theme(&PlainTheme), f.to_string());
+        assert_eq!(f.display_with_theme(&Backticks), "mod `m`::fn `f`(a: u8)");
+        let mut closure = TypeStruct::new(Type::Closure, "c");
+        closure.args = vec!["a".to_owned(), "b".to_owned(), "-> u8".to_owned()];
+        assert_eq!(closure.display_with_theme(&PlainTheme), "c: |a, b| -> u8");
+    }
+
+    #[test]
+    fn display_wrapped() {
+        let item = |line| {
+            let (ty, name, args) = Type::parse_item_line(line).unwrap();
+            let mut t = TypeStruct::new(ty, &name);
+            t.args = args;
+            t
+        };
+        let f = item("fn f<T: Clone>(a: u8, b: (u8, u16)) -> Vec<u8> where T: Copy");
+        assert!(f.args_bracket_balanced());
+        assert_eq!(f.display_wrapped(80),
+                   "fn f<T: Clone>(a: u8, b: (u8, u16)) -> Vec<u8> where T: Copy");
+        assert_eq!(f.display_wrapped(20),
+                   "fn f<T: Clone>(\n    a: u8,\n    b: (u8, u16),\n) -> Vec<u8> where T: Copy");
+        assert_eq!(item("fn get(&self, a: u8) -> u8").display_wrapped(10),
+                   "fn get(\n    &self,\n    a: u8,\n) -> u8");
+        // Nothing to split.
+        assert_eq!(item("fn g() -> u8").display_wrapped(5), "fn g() -> u8");
+        assert_eq!(item("struct S").display_wrapped(5), "struct S");
+        // Widths are counted in characters, `é` and `§` taking two bytes each.
+        let mut g = item("fn é(a: u8)");
+        g.parent = Some(Box::new(TypeStruct::new(Type::Mod, "m")));
+        assert_eq!(g.to_string().chars().count(), 17);
+        assert_eq!(g.display_wrapped(17), "mod m§fn é(a: u8)");
+        assert_eq!(g.display_wrapped(16), "mod m§fn é(\n    a: u8,\n)");
+    }
+
+    #[test]
+    fn args_without_self() {
+        let item = |line| {
+            let (ty, name, args) = Type::parse_item_line(line).unwrap();
+            let mut t = TypeStruct::new(ty, &name);
+            t.args = args;
+            t
+        };
+        assert_eq!(item("fn get(&self, a: u8) -> u8").args_without_self(), vec!["a: u8"]);
+        assert_eq!(item("fn get<'a>(&'a mut self, a: (u8, u8), b: u8)").args_without_self(),
+                   vec!["a: (u8, u8)", "b: u8"]);
+        assert_eq!(item("fn boxed(self: Box<Self>, a: u8)").args_without_self(), vec!["a: u8"]);
+        assert_eq!(item("fn free<T: Fn(u8)>(a: T, selfish: u8)").args_without_self(),
+                   vec!["a: T", "selfish: u8"]);
+        assert!(item("fn none(self) -> u8").args_without_self().is_empty());
+        assert!(item("struct S(u8)").args_without_self().is_empty());
+    }
+
+    #[test]
+    fn to_tokens() {
+        let mut f = TypeStruct::new(Type::Fn, "foo");
+        f.args = vec!["(a: i32)".to_owned()];
+        assert_eq!(f.to_tokens(), vec!["fn", "foo", "(", "a", ":", "i32", ")"]);
+        f.args = vec!["<'a>(a: &'a std::string::String)".to_owned(), "->".to_owned(),
+                      "u8".to_owned()];
+        assert_eq!(f.to_tokens(),
+                   vec!["fn", "foo", "<", "'a", ">", "(", "a", ":", "&", "'a", "std", "::",
+                        "string", "::", "String", ")", "->", "u8"]);
+    }
+
+    #[test]
+    fn every_macro_alias_is_a_macro() {
+        assert_eq!(Type::macro_aliases(), &["macro", "macro_rules", "macro_rules!"]);
+        assert!(Type::macro_aliases().iter().all(|a| Type::from(a) == Type::Macro));
+    }
+
+    fn child(ty: Type, name: &str, parent: TypeStruct) -> TypeStruct {
+        let mut t = TypeStruct::new(ty, name);
+        t.parent = Some(Box::new(parent));
+        t
+    }
+
+    #[test]
+    fn shared_prefix() {
+        let a = child(Type::Fn, "a", path(&["m"]));
+        let b = child(Type::Struct, "B", path(&["m"]));
+        assert_eq!(TypeStruct::shared_prefix(&a, &b), vec![path(&["m"])]);
+        let c = child(Type::Fn, "c", path(&["n"]));
+        assert!(TypeStruct::shared_prefix(&a, &c).is_empty());
+        let d = child(Type::Fn, "d", path(&["m", "x"]));
+        let e = child(Type::Fn, "e", path(&["m", "y"]));
+        assert_eq!(TypeStruct::shared_prefix(&d, &e), vec![path(&["m"])]);
+    }
+
+    #[test]
+    fn bound_under_fn() {
+        let mut bound = child(Type::Bound, "T", TypeStruct::new(Type::Fn, "f"));
+        bound.args = vec!["Clone".to_owned(), "Send".to_owned()];
+        assert_eq!(bound.to_string(), "fn f§T: Clone + Send");
+        let mut lifetime = TypeStruct::new(Type::Bound, "'a");
+        lifetime.args = vec!["'b".to_owned()];
+        assert_eq!(lifetime.to_string(), "'a: 'b");
+    }
+
+    #[test]
+    fn serialized_len() {
+        let comment = EventType::Comment("a\tb§ é\\".to_owned());
+        assert_eq!(comment.serialized_len(), comment.to_string().len());
+        let mut f = child(Type::Fn, "f", path(&["m"]));
+        f.args.push("(a: u8)".to_owned());
+        f.docs.push("doc".to_owned());
+        f.span = Some(Span { start: 10, end: 200 });
+        let f = EventType::Type(f);
+        assert_eq!(f.serialized_len(), f.to_string().len());
+        assert_eq!(EventType::Blank(3).serialized_len(), 0);
+    }
+
+    #[test]
+    fn args_iter() {
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        assert_eq!(f.args_iter().count(), 0);
+        f.args = vec!["<T>(a: T)".to_owned(), "->".to_owned(), "T".to_owned()];
+        assert_eq!(f.args_iter().collect::<Vec<_>>(), vec!["<T>(a: T)", "->", "T"]);
+    }
+
+    #[test]
+    fn closure_rendering() {
+        let mut closure = TypeStruct::new(Type::Closure, "");
+        closure.args = vec!["x: u8".to_owned()];
+        assert_eq!(closure.to_string(), "|x: u8|");
+        closure.args.push("-> bool".to_owned());
+        assert_eq!(closure.to_string(), "|x: u8| -> bool");
+        let closure = child(Type::Closure, "", TypeStruct::new(Type::Fn, "f"));
+        assert_eq!(closure.to_string(), "fn f§||");
+    }
+
+    #[test]
+    fn to_rust_stub() {
+        assert_eq!(TypeStruct::new(Type::Struct, "S").to_rust_stub(), "struct S;");
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        assert_eq!(f.to_rust_stub(), "fn f() { unimplemented!() }");
+        f.args = vec!["(a: u8)".to_owned(), "->".to_owned(), "u8".to_owned()];
+        assert_eq!(f.to_rust_stub(), "fn f(a: u8) -> u8 { unimplemented!() }");
+        let mut m = TypeStruct::new(Type::Mod, "m");
+        assert_eq!(m.to_rust_stub(), "mod m {}");
+        m.inline = false;
+        assert_eq!(m.to_rust_stub(), "mod m;");
+        assert_eq!(TypeStruct::new(Type::Variant, "A").to_rust_stub(), "");
+    }
+
+    #[test]
+    fn event_hash_set() {
+        use std::collections::HashSet;
+
+        let mut events = HashSet::new();
+        assert!(events.insert(EventType::Comment("a".to_owned())));
+        assert!(!events.insert(EventType::Comment("a".to_owned())));
+        assert!(events.insert(EventType::Comment("b".to_owned())));
+        assert!(events.insert(EventType::FileComment("a".to_owned())));
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn depth_histogram_counts() {
+        let items = vec![
+            path(&["m"]),
+            child(Type::Fn, "f", path(&["m"])),
+            child(Type::Fn, "g", path(&["m"])),
+            child(Type::Fn, "h", path(&["m", "n"])),
+        ];
+        let expected = vec![(0, 1), (1, 2), (2, 1)].into_iter().collect::<BTreeMap<_, _>>();
+        assert_eq!(depth_histogram(&items), expected);
+        assert!(depth_histogram(&[]).is_empty());
+    }
+
+    #[test]
+    fn sanitize_name() {
+        assert_eq!(TypeStruct::new(Type::Struct, "Foo_1").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Struct, "").sanitize_name(), Err(NameError::Empty));
+        assert_eq!(TypeStruct::new(Type::Struct, "Foo Bar").sanitize_name(),
+                   Err(NameError::InvalidCharacter { name: "Foo Bar".to_owned(), c: ' ' }));
+        assert_eq!(TypeStruct::new(Type::Fn, "1f").sanitize_name(),
+                   Err(NameError::StartsWithDigit("1f".to_owned())));
+        assert_eq!(TypeStruct::new(Type::Use, "std::io::*").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Struct, "fn").sanitize_name(),
+                   Err(NameError::Keyword("fn".to_owned())));
+        assert_eq!(TypeStruct::new(Type::Fn, "self").sanitize_name(),
+                   Err(NameError::Keyword("self".to_owned())));
+        assert_eq!(NameError::Keyword("fn".to_owned()).to_string(), "name \"fn\" is a keyword");
+        assert_eq!(TypeStruct::new(Type::Struct, "r#fn").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Bound, "'static").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Bound, "'fn").sanitize_name(),
+                   Err(NameError::Keyword("'fn".to_owned())));
+        assert_eq!(TypeStruct::new(Type::Fn, "gen").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Use, "::std::io").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Use, "crate::a::b").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Use, "super::self::*").sanitize_name(), Ok(()));
+        assert_eq!(TypeStruct::new(Type::Use, "std::fn::x").sanitize_name(),
+                   Err(NameError::Keyword("std::fn::x".to_owned())));
+        assert_eq!(TypeStruct::new(Type::Use, "::").sanitize_name(), Err(NameError::Empty));
+        assert_eq!(TypeStruct::new(Type::Use, "::::a").sanitize_name(), Err(NameError::Empty));
+    }
+
+    #[test]
+    fn render_scope_delimiters() {
+        assert_eq!(Type::Mod.render_opening("m"), "mod m {");
+        assert_eq!(Type::Mod.render_closing(), "}");
+        assert_eq!(Type::Impl.render_opening("Foo"), "impl Foo {");
+        assert_eq!(Type::Impl.render_opening(""), "impl {");
+        assert_eq!(Type::Impl.render_closing(), "}");
+        assert_eq!(Type::Const.render_opening("FOO: u32"), "const FOO: u32;");
+        assert_eq!(Type::Const.render_closing(), "");
+    }
+
+    #[test]
+    fn with_doc() {
+        let f = TypeStruct::new(Type::Fn, "f").with_doc(vec!["Does things.".to_owned(),
+                                                             String::new(),
+                                                             "More.".to_owned()]);
+        assert_eq!(f.docs.len(), 3);
+        assert_eq!(f.render_with_docs(), "/// Does things.\n///\n/// More.\nfn f");
+        assert_eq!(TypeStruct::new(Type::Fn, "f").render_with_docs(), "fn f");
+    }
+
+    #[test]
+    fn from_type_expr() {
+        assert_eq!(Type::from_type_expr("dyn Trait"), Type::Dyn);
+        assert_eq!(Type::from_type_expr("impl Iterator<Item = u8>"), Type::ImplTrait);
+        assert_eq!(Type::from_type_expr("impl"), Type::Impl);
+        assert_eq!(Type::from_type_expr("dyn"), Type::Unknown);
+        assert_eq!(Type::from_type_expr(""), Type::Unknown);
+    }
+
+    #[test]
+    fn matches_glob() {
+        let test = TypeStruct::new(Type::Fn, "test_foo");
+        assert!(test.matches_glob("test_*"));
+        assert!(!test.matches_glob("*_bar"));
+        let bar = child(Type::Fn, "bar", path(&["m"]));
+        assert!(bar.matches_glob("*::bar"));
+        assert!(!TypeStruct::new(Type::Fn, "bar").matches_glob("*::bar"));
+        // `*` doesn't cross path segments, `**` does.
+        let nested = child(Type::Fn, "bar", path(&["a", "b"]));
+        assert!(!nested.matches_glob("*::bar"));
+        assert!(nested.matches_glob("**::bar"));
+    }
+
+    #[test]
+    fn unknown_raw_keyword() {
+        let generator = TypeStruct::new(Type::Unknown, "x").with_raw_keyword("gen");
+        assert_eq!(generator.to_string(), "gen x");
+        assert_eq!(generator.raw_keyword.as_deref(), Some("gen"));
+        // Known types keep their keyword.
+        assert_eq!(TypeStruct::new(Type::Fn, "f").with_raw_keyword("func").to_string(), "fn f");
+    }
+
+    #[test]
+    fn is_documentation() {
+        assert!(EventType::Comment(String::new()).is_documentation());
+        assert!(EventType::TrailingComment(String::new()).is_documentation());
+        assert!(EventType::InnerComment(String::new()).is_documentation());
+        assert!(EventType::FileComment(String::new()).is_documentation());
+        assert!(!EventType::File(String::new()).is_documentation());
+        assert!(!EventType::Type(TypeStruct::empty()).is_documentation());
+        assert!(!EventType::InScope.is_documentation());
+        assert!(!EventType::OutScope.is_documentation());
+        assert!(!EventType::Blank(1).is_documentation());
+    }
+
+    #[test]
+    fn common_root() {
+        let a = child(Type::Fn, "a", path(&["m", "x"]));
+        let b = child(Type::Fn, "b", path(&["m", "x"]));
+        let c = child(Type::Fn, "c", path(&["m", "y"]));
+        assert_eq!(TypeStruct::common_root(&[&a, &b]), Some(path(&["m", "x"])));
+        assert_eq!(TypeStruct::common_root(&[&a, &b, &c]), Some(path(&["m"])));
+        let d = child(Type::Fn, "d", path(&["n"]));
+        assert_eq!(TypeStruct::common_root(&[&a, &c, &d]), None);
+        assert_eq!(TypeStruct::common_root(&[]), None);
+    }
+
+    #[test]
+    fn display_alternate() {
+        let mut f = child(Type::Fn, "f", path(&["a", "b"]));
+        f.args.push("(a: u8)".to_owned());
+        assert_eq!(format!("{}", f), "mod a§mod b§fn f(a: u8)");
+        assert_eq!(format!("{:#}", f), "mod a\n    mod b\n        fn f(a: u8)");
+        let root = TypeStruct::new(Type::Struct, "S");
+        assert_eq!(format!("{:#}", root), format!("{}", root));
+    }
+
+    #[test]
+    fn variant_count() {
+        let mut e = TypeStruct::new(Type::Enum, "E");
+        assert_eq!(e.variant_count(), None);
+        e.variant_count = Some(3);
+        assert_eq!(e.variant_count(), Some(3));
+        let mut s = TypeStruct::new(Type::Struct, "S");
+        s.variant_count = Some(3);
+        assert_eq!(s.variant_count(), None);
+    }
+
+    #[test]
+    fn as_mut_type() {
+        let mut event = EventType::Type(TypeStruct::new(Type::Fn, "f"));
+        event.as_mut_type().unwrap().name = Cow::Borrowed("g");
+        assert_eq!(event, EventType::Type(TypeStruct::new(Type::Fn, "g")));
+        assert!(EventType::Comment("c".to_owned()).as_mut_type().is_none());
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = TypeRegistry::new();
+        registry.register("yeet", Type::Fn);
+        assert_eq!(registry.get("yeet"), Some(Type::Fn));
+        assert_eq!(Type::from_registry("yeet", &registry), Type::Fn);
+        assert_eq!(Type::from_registry("struct", &registry), Type::Struct);
+        assert_eq!(Type::from("yeet"), Type::Variant);
+        assert_eq!(Type::from_registry("yeet", &TypeRegistry::new()), Type::Variant);
+    }
+
+    #[test]
+    fn truncate_to_depth() {
+        let f = child(Type::Fn, "f", path(&["a", "b", "c", "d"]));
+        assert_eq!(f.get_depth(false), 4);
+        let truncated = f.truncate_to_depth(2);
+        assert_eq!(truncated.get_depth(false), 2);
+        // The closest parents are the ones kept.
+        assert_eq!(truncated.full_path(), "c::d::f");
+        assert_eq!(f.truncate_to_depth(0).full_path(), "f");
+        assert_eq!(f.truncate_to_depth(10), f);
+    }
+
+    #[test]
+    fn into_parts() {
+        let mut f = child(Type::Fn, "f", path(&["m"]));
+        f.args.push("()".to_owned());
+        assert_eq!(f.into_parts(),
+                   (Type::Fn, "f".to_owned(), vec!["()".to_owned()], Some(Box::new(path(&["m"])))));
+    }
+
+    #[test]
+    fn sort_rank() {
+        let mut items = [
+            TypeStruct::new(Type::Use, "u"),
+            TypeStruct::new(Type::Fn, "f"),
+            TypeStruct::new(Type::Const, "C"),
+            TypeStruct::new(Type::Struct, "S"),
+            TypeStruct::new(Type::Mod, "m"),
+            TypeStruct::new(Type::Enum, "E"),
+            TypeStruct::new(Type::Trait, "T"),
+        ];
+        items.sort_by_key(|t| t.ty.sort_rank());
+        assert_eq!(items.iter().map(|t| t.name.as_ref()).collect::<Vec<_>>(),
+                   vec!["m", "S", "E", "T", "f", "C", "u"]);
+    }
+
+    #[test]
+    fn impl_target() {
+        let inherent = TypeStruct::inherent_impl("Foo");
+        assert!(inherent.is_impl_block());
+        assert_eq!(inherent.impl_target(), Some("Foo"));
+        assert_eq!(TypeStruct::trait_impl("Display", "Foo").impl_target(), Some("Foo"));
+        let (ty, name, args) = Type::parse_item_line("impl<T> Debug for Vec<T> {").unwrap();
+        let mut parsed = TypeStruct::new(ty, &name);
+        parsed.args = args;
+        assert_eq!(parsed.impl_target(), Some("Vec<T>"));
+        let f = TypeStruct::new(Type::Fn, "f");
+        assert!(!f.is_impl_block());
+        assert_eq!(f.impl_target(), None);
+    }
+
+    #[test]
+    fn render_line() {
+        let f = EventType::Type(TypeStruct::new(Type::Fn, "f"));
+        assert_eq!(f.render_line(0), "t fn\tf");
+        assert_eq!(f.render_line(2), format!("{}{}t fn\tf", INDENT, INDENT));
+        assert_eq!(EventType::Blank(1).render_line(2), "");
+    }
+
+    #[test]
+    fn impl_kinds() {
+        let inherent = TypeStruct::inherent_impl("Foo");
+        assert_eq!(inherent.impl_kind, Some(ImplKind::Inherent));
+        assert_eq!(inherent.to_string(), "impl Foo");
+        let for_trait = TypeStruct::trait_impl("Display", "Foo");
+        assert_eq!(for_trait.impl_kind, Some(ImplKind::Trait));
+        assert_eq!(for_trait.to_string(), "impl Display for Foo");
+        assert_ne!(inherent, for_trait);
+    }
+
+    #[test]
+    fn walk() {
+        let f = child(Type::Fn, "f", path(&["a", "b"]));
+        let mut names = Vec::new();
+        f.walk(|t| names.push(t.name.to_string()));
+        assert_eq!(names, vec!["f", "b", "a"]);
+    }
+
+    #[test]
+    fn abbreviations_are_unique() {
+        let abbreviations = Type::all().iter().map(|t| t.abbreviation()).collect::<BTreeSet<_>>();
+        assert_eq!(abbreviations.len(), Type::all().len());
+        assert!(abbreviations.iter().all(|a| !a.is_empty() && a.chars().count() <= 2));
+    }
+
+    #[test]
+    fn is_generic() {
+        let mut foo = TypeStruct::new(Type::Fn, "foo");
+        foo.args = vec!["<T>(a: T)".to_owned()];
+        assert!(foo.is_generic());
+        let mut bar = TypeStruct::new(Type::Fn, "bar");
+        assert!(!bar.is_generic());
+        bar.args = vec!["()".to_owned(), "->".to_owned(), "Vec<u8>".to_owned()];
+        assert!(!bar.is_generic());
+    }
+
+    #[test]
+    fn type_annotation() {
+        let mut foo = TypeStruct::new(Type::Const, "FOO");
+        foo.type_annotation = Some("u32".to_owned());
+        assert_eq!(foo.to_string(), "const FOO: u32");
+        let mut alias = TypeStruct::new(Type::Type, "Alias");
+        alias.type_annotation = Some("Vec<u8>".to_owned());
+        assert_eq!(alias.to_string(), "type Alias = Vec<u8>");
+        let mut bar = TypeStruct::new(Type::Static, "BAR");
+        bar.type_annotation = Some("&str".to_owned());
+        assert_eq!(bar.to_string(), "static BAR: &str");
+    }
+
+    #[test]
+    fn args_bracket_balanced() {
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.args = vec!["<T: Into<U>, U>(a: [T; 2])".to_owned(), "->".to_owned(), "U".to_owned()];
+        assert!(f.args_bracket_balanced());
+        f.args = vec!["(a: Vec<u8".to_owned(), ">)".to_owned()];
+        assert!(!f.args_bracket_balanced());
+        f.args = vec!["(a: u8])".to_owned()];
+        assert!(!f.args_bracket_balanced());
+    }
+
+    #[test]
+    fn from_static_borrows() {
+        let t = TypeStruct::from_static(Type::Struct, "Foo");
+        assert!(matches!(t.name, Cow::Borrowed("Foo")));
+        assert_eq!(t, TypeStruct::new(Type::Struct, "Foo"));
+        assert!(matches!(TypeStruct::new(Type::Struct, "Foo").name, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn discriminant() {
+        let mut a = TypeStruct::new(Type::Variant, "A");
+        assert_eq!(a.to_string(), "variant A");
+        a.discriminant = Some("1 << 2".to_owned());
+        assert_eq!(a.to_string(), "variant A = 1 << 2");
+        assert_ne!(a, TypeStruct::new(Type::Variant, "A"));
+    }
+
+    #[test]
+    fn display_relative_to() {
+        let f = child(Type::Fn, "f", path(&["a", "b", "c"]));
+        assert_eq!(f.display_relative_to(&path(&["a"])), "b::c::f");
+        assert_eq!(f.display_relative_to(&path(&["a", "b", "c"])), "f");
+        assert_eq!(f.display_relative_to(&path(&["x"])), "a::b::c::f");
+        // `b` alone isn't an ancestor, `a::b` is.
+        assert_eq!(f.display_relative_to(&path(&["b"])), "a::b::c::f");
+    }
+
+    #[test]
+    fn ansi_colors() {
+        for t in Type::all() {
+            let code = t.ansi_color().parse::<u8>().unwrap();
+            assert!((30..=39).contains(&code) && code != 38, "{:?}", t);
+        }
+        assert_eq!(Type::Unknown.ansi_color(), "39");
+    }
+
+    #[test]
+    fn prepend_module() {
+        let f = child(Type::Fn, "f", path(&["a", "b"])).prepend_module("mycrate");
+        assert_eq!(f.full_path(), "mycrate::a::b::f");
+        assert_eq!(f.get_depth(false), 3);
+        assert_eq!(TypeStruct::new(Type::Fn, "f").prepend_module("mycrate").full_path(),
+                   "mycrate::f");
+    }
+
+    #[test]
+    fn non_item_keywords() {
+        for keyword in &["where", "for", "as", "in", "dyn"] {
+            assert_eq!(Type::from(keyword), Type::Unknown, "{}", keyword);
+        }
+        assert_eq!(Type::from("Foo"), Type::Variant);
+    }
+
+    #[test]
+    fn extend_args() {
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.extend(vec!["(a: u8)".to_owned(), "->".to_owned()]);
+        f.extend(Some("u8"));
+        assert_eq!(f.args, vec!["(a: u8)", "->", "u8"]);
+    }
+
+    #[test]
+    fn from_doc_kind() {
+        assert_eq!(Type::from_doc_kind("function"), Some(Type::Fn));
+        assert_eq!(Type::from_doc_kind("module"), Some(Type::Mod));
+        assert_eq!(Type::from_doc_kind("assoc_type"), Some(Type::Type));
+        assert_eq!(Type::from_doc_kind("primitive"), None);
+        assert_eq!(Type::from_doc_kind(""), None);
+    }
+
+    #[test]
+    fn collapse_single_child_mods() {
+        let f = child(Type::Fn, "f", path(&["a", "b", "c"])).collapse_single_child_mods();
+        assert_eq!(f.get_depth(false), 1);
+        assert_eq!(f.parent.as_ref().unwrap().name, "a::b::c");
+        assert_eq!(f.full_path(), "a::b::c::f");
+        // Modules separated by another item aren't merged.
+        let g = child(Type::Fn, "g", child(Type::Mod, "m", TypeStruct::new(Type::Trait, "T")));
+        let g = child(Type::Closure, "", child(Type::Mod, "n", g));
+        let collapsed = g.clone().collapse_single_child_mods();
+        assert_eq!(collapsed, g);
+        // The merged module is private if any of them is.
+        let mut b = child(Type::Mod, "b", TypeStruct::new(Type::Mod, "a"));
+        b.visibility = Some("pub".to_owned());
+        let h = child(Type::Fn, "h", b.clone()).collapse_single_child_mods();
+        assert_eq!(h.parent.as_ref().unwrap().name, "a::b");
+        assert_eq!(h.parent.as_ref().unwrap().visibility, None);
+        b.parent.as_mut().unwrap().visibility = Some("pub".to_owned());
+        b.visibility = Some("pub(crate)".to_owned());
+        let h = child(Type::Fn, "h", b.clone()).collapse_single_child_mods();
+        assert_eq!(h.parent.as_ref().unwrap().visibility.as_deref(), Some("pub(crate)"));
+        b.visibility = Some("pub".to_owned());
+        let h = child(Type::Fn, "h", b).collapse_single_child_mods();
+        assert_eq!(h.parent.as_ref().unwrap().visibility.as_deref(), Some("pub"));
+    }
+
+    #[test]
+    fn gen_keyword() {
+        assert_eq!(Type::from("gen"), Type::Gen);
+        assert_eq!(Type::Gen.to_string(), "gen");
+        assert_eq!(Type::from(&Type::Gen.to_string()), Type::Gen);
+    }
+
+    #[test]
+    fn is_method() {
+        let free = child(Type::Fn, "free", path(&["m"]));
+        assert!(!free.is_method());
+        let new = child(Type::Fn, "new", TypeStruct::inherent_impl("Foo"));
+        assert!(new.is_method());
+        assert!(!new.takes_self());
+        let mut method = child(Type::Fn, "get", TypeStruct::inherent_impl("Foo"));
+        method.args = vec!["(&self)".to_owned(), "->".to_owned(), "u8".to_owned()];
+        assert!(method.is_method());
+        assert!(method.takes_self());
+        // Functions nested in a method aren't methods.
+        assert!(!child(Type::Fn, "inner", method).is_method());
+    }
+
+    #[test]
+    fn markdown_header() {
+        assert_eq!(child(Type::Struct, "S", path(&["m"])).markdown_header(2), "## `struct S`");
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.args.push("()".to_owned());
+        assert_eq!(f.markdown_header(3), "### `fn f()`");
+        assert_eq!(f.markdown_header(9), "###### `fn f()`");
+    }
+
+    #[test]
+    fn is_effectively_public() {
+        let mut private = TypeStruct::new(Type::Mod, "private");
+        let mut f = child(Type::Fn, "f", private.clone());
+        f.visibility = Some("pub".to_owned());
+        assert!(!f.is_effectively_public());
+        private.visibility = Some("pub".to_owned());
+        f.parent = Some(Box::new(private));
+        assert!(f.is_effectively_public());
+        f.visibility = Some("pub(crate)".to_owned());
+        assert!(!f.is_effectively_public());
+    }
+
+    #[test]
+    fn split_keyword() {
+        assert_eq!(Type::split_keyword("mod foo"), (Type::Mod, "foo"));
+        assert_eq!(Type::split_keyword("fn bar()"), (Type::Fn, "bar()"));
+        assert_eq!(Type::split_keyword("  struct  S"), (Type::Struct, "S"));
+        assert_eq!(Type::split_keyword("Foo"), (Type::Unknown, "Foo"));
+    }
+
+    #[test]
+    fn without_args() {
+        let mut a = TypeStruct::new(Type::Fn, "foo");
+        a.args = vec!["(a: u8)".to_owned()];
+        let mut b = TypeStruct::new(Type::Fn, "foo");
+        b.args = vec!["<T>(b: T)".to_owned()];
+        assert_ne!(a, b);
+        assert_eq!(a.without_args(), b.without_args());
+        assert!(a.without_args().args.is_empty());
+    }
+
+    #[test]
+    fn categories() {
+        let expected = [
+            (Type::Struct, Category::Data),
+            (Type::Mod, Category::Container),
+            (Type::Enum, Category::Data),
+            (Type::Fn, Category::Callable),
+            (Type::Const, Category::Data),
+            (Type::Static, Category::Data),
+            (Type::Type, Category::Data),
+            (Type::Variant, Category::Data),
+            (Type::Impl, Category::Container),
+            (Type::Use, Category::Reexport),
+            (Type::Macro, Category::Callable),
+            (Type::Trait, Category::Container),
+            (Type::Bound, Category::Synthetic),
+            (Type::Closure, Category::Callable),
+            (Type::Dyn, Category::Synthetic),
+            (Type::ImplTrait, Category::Synthetic),
+            (Type::Gen, Category::Callable),
+            (Type::Unknown, Category::Synthetic),
+        ];
+        assert_eq!(expected.len(), Type::all().len());
+        for &(t, category) in &expected {
+            assert_eq!(t.category(), category, "{:?}", t);
+        }
+    }
+
+    #[test]
+    fn parse_generics() {
+        assert_eq!(Type::parse_generics("<T: Into<U>, U>"), vec!["T: Into<U>", "U"]);
+        assert_eq!(Type::parse_generics("<'a, T: 'a + Fn(u8) -> u8>"),
+                   vec!["'a", "T: 'a + Fn(u8) -> u8"]);
+        assert_eq!(Type::parse_generics("T, const N: usize"), vec!["T", "const N: usize"]);
+        assert!(Type::parse_generics("<>").is_empty());
+    }
+
+    #[test]
+    fn as_use_reexport() {
+        assert_eq!(TypeStruct::new(Type::Use, "foo::Bar").as_use_reexport(),
+                   Some(("foo::Bar".to_owned(), None)));
+        let (ty, name, args) = Type::parse_item_line("pub use foo::Bar as Baz;").unwrap();
+        let mut alias = TypeStruct::new(ty, &name);
+        alias.args = args;
+        assert_eq!(alias.as_use_reexport(),
+                   Some(("foo::Bar".to_owned(), Some("Baz".to_owned()))));
+        assert_eq!(TypeStruct::new(Type::Struct, "Bar").as_use_reexport(), None);
+    }
+
+    #[test]
+    fn macro_kinds() {
+        let rules = TypeStruct::new(Type::Macro, "foo").with_raw_keyword("macro_rules!");
+        assert_eq!(rules.macro_kind, Some(MacroKind::Rules));
+        assert_eq!(rules.to_string(), "macro_rules! foo");
+        let decl = TypeStruct::new(Type::Macro, "foo").with_raw_keyword("macro");
+        assert_eq!(decl.macro_kind, Some(MacroKind::Decl2));
+        assert_eq!(decl.to_string(), "macro foo");
+        assert_ne!(rules, decl);
+        assert_eq!(MacroKind::from_keyword("proc_macro_derive"), Some(MacroKind::Proc));
+        assert_eq!(MacroKind::from_keyword("fn"), None);
+    }
+
+    #[test]
+    fn flatten_to_paths() {
+        let items = vec![
+            child(Type::Fn, "f", path(&["b"])),
+            child(Type::Fn, "g", child(Type::Impl, "", path(&["a"]))),
+            child(Type::Struct, "S", path(&["a"])),
+        ];
+        assert_eq!(TypeStruct::flatten_to_paths(&items), vec!["a", "a::S", "a::g", "b", "b::f"]);
+    }
+
+    #[test]
+    fn str_eq_type() {
+        assert_eq!("struct", Type::Struct);
+        assert_ne!("fn", Type::Struct);
+        assert!(*"mod" == Type::Mod);
+        assert_ne!("impl", Type::ImplTrait);
+        assert_eq!("impl_trait", Type::ImplTrait);
+    }
+
+    #[test]
+    fn retain_args() {
+        let mut t = TypeStruct::new(Type::Struct, "S");
+        t.args = vec!["'a".to_owned(), "T".to_owned(), "'b".to_owned(), "U".to_owned()];
+        t.retain_args(|a| !a.starts_with('\''));
+        assert_eq!(t.args, vec!["T", "U"]);
+    }
+
+    #[test]
+    fn merge_docs_from() {
+        let mut t = TypeStruct::new(Type::Fn, "f").with_doc(vec!["a".to_owned(), "b".to_owned()]);
+        let new = vec!["b".to_owned(), "c".to_owned(), "c".to_owned()];
+        t.merge_docs_from(&new);
+        assert_eq!(t.docs, vec!["a", "b", "c"]);
+        t.merge_docs_from(&new);
+        assert_eq!(t.docs, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn raw_identifiers() {
+        assert_eq!(Type::from("r#type"), Type::Variant);
+        assert_eq!(Type::from("r#struct"), Type::Variant);
+        assert_eq!(Type::from("type"), Type::Type);
+        assert_eq!(TypeStruct::new(Type::Fn, "r#type").sanitize_name(), Ok(()));
+    }
+
+    #[test]
+    fn check_roundtrip() {
+        assert_eq!(Type::check_roundtrip(), Ok(()));
+    }
+
+    #[test]
+    fn parent_of_type_mut() {
+        let mut f = child(Type::Fn, "f", child(Type::Impl, "", path(&["a", "m"])));
+        f.parent_of_type_mut(Type::Mod).unwrap().name = Cow::Borrowed("renamed");
+        assert_eq!(f.full_path(), "a::renamed::f");
+        assert!(f.parent_of_type_mut(Type::Trait).is_none());
+        // `self` isn't one of its own parents.
+        assert!(f.parent_of_type_mut(Type::Fn).is_none());
+    }
+
+    #[test]
+    fn is_type_definition() {
+        let definitions = [Type::Struct, Type::Enum, Type::Trait, Type::Fn, Type::Const,
+                           Type::Static, Type::Type, Type::Mod];
+        for t in Type::all() {
+            assert_eq!(t.is_type_definition(), definitions.contains(t), "{:?}", t);
+        }
+        assert!(!Type::Use.is_type_definition());
+        assert!(Type::Struct.is_type_definition());
+    }
+
+    #[test]
+    fn estimate_complexity() {
+        let parse = |line: &str| {
+            let (ty, name, args) = Type::parse_item_line(line).unwrap();
+            let mut t = TypeStruct::new(ty, &name);
+            t.args = args;
+            t
+        };
+        assert_eq!(parse("fn f(a: u8)").estimate_complexity(), 1);
+        assert_eq!(parse("fn f()").estimate_complexity(), 0);
+        // 2 parameters, 2 generic parameters and 3 bounds.
+        let generic = parse("fn g<T: Clone + Send, U>(a: T, b: U) -> T where U: Debug {");
+        assert_eq!(generic.estimate_complexity(), 2 + 2 * 2 + 3);
+    }
+
+    #[test]
+    fn dedup_args() {
+        let mut t = TypeStruct::new(Type::Fn, "f");
+        t.extend(vec!["a", "b", "a", "c", "b"]);
+        t.dedup_args();
+        assert_eq!(t.args, vec!["a", "b", "c"]);
     }
 }
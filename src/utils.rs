@@ -36,3 +36,299 @@ pub fn write_file_comment(comment: &str) -> String {
 pub fn write_file(file: &str) -> String {
     format!("{}{}{}", FILE, file, END_INFO)
 }
+
+// Returns the list marker (`- `, `* `, `+ ` or `1. `) starting `line`, if any.
+fn list_marker(line: &str) -> Option<&str> {
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        return Some(&line[..2]);
+    }
+    let digits = line.find(|c: char| !c.is_ascii_digit()).unwrap_or(line.len());
+    if digits > 0 && line[digits..].starts_with(". ") {
+        Some(&line[..digits + 2])
+    } else {
+        None
+    }
+}
+
+struct Paragraph {
+    prefix: String,
+    indent: String,
+    words: Vec<String>,
+}
+
+impl Paragraph {
+    fn flush(&mut self, width: usize, out: &mut Vec<String>) {
+        let mut line = self.prefix.clone();
+        let mut empty = true;
+        for word in self.words.drain(..) {
+            if !empty && line.chars().count() + 1 + word.chars().count() > width {
+                out.push(line);
+                line = self.indent.clone();
+                empty = true;
+            }
+            if !empty {
+                line.push(' ');
+            }
+            line.push_str(&word);
+            empty = false;
+        }
+        if !empty {
+            out.push(line);
+        }
+    }
+}
+
+// Code blocks, fenced or indented by four spaces, are kept as is, as well as blank lines,
+// headings, tables and block quotes. List items keep their indentation and marker. A trailing
+// newline is kept.
+pub fn reflow_comment(text: &str, width: usize) -> String {
+    let mut out = Vec::new();
+    let mut paragraph: Option<Paragraph> = None;
+    let mut fence: Option<&str> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let opens = ["```", "~~~"].iter().find(|f| trimmed.starts_with(**f)).cloned();
+        // Indented code blocks can't interrupt a paragraph.
+        let indented_code = paragraph.is_none() &&
+                            (line.starts_with("    ") || line.starts_with('\t'));
+        if fence.is_some() || opens.is_some() || (indented_code && !trimmed.is_empty()) {
+            if let Some(mut p) = paragraph.take() {
+                p.flush(width, &mut out);
+            }
+            match (fence, opens) {
+                (None, Some(opens)) => fence = Some(opens),
+                (Some(f), _) if trimmed.starts_with(f) => fence = None,
+                _ => {}
+            }
+            out.push(line.to_owned());
+            continue;
+        }
+        if let Some(marker) = list_marker(trimmed) {
+            if let Some(mut p) = paragraph.take() {
+                p.flush(width, &mut out);
+            }
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let prefix = format!("{}{}", indent, marker);
+            paragraph = Some(Paragraph {
+                indent: " ".repeat(prefix.chars().count()),
+                prefix,
+                words: trimmed[marker.len()..].split_whitespace().map(|w| w.to_owned()).collect(),
+            });
+            continue;
+        }
+        // Blank lines, headings, tables and block quotes are kept as is.
+        if trimmed.is_empty() || trimmed.starts_with(&['#', '|', '>'][..]) {
+            if let Some(mut p) = paragraph.take() {
+                p.flush(width, &mut out);
+            }
+            out.push(line.to_owned());
+            continue;
+        }
+        let continues = match paragraph {
+            Some(ref p) => p.prefix.is_empty() || line.starts_with(char::is_whitespace),
+            None => false,
+        };
+        if !continues {
+            if let Some(mut p) = paragraph.take() {
+                p.flush(width, &mut out);
+            }
+            paragraph = Some(Paragraph {
+                prefix: String::new(),
+                indent: String::new(),
+                words: Vec::new(),
+            });
+        }
+        if let Some(ref mut p) = paragraph {
+            p.words.extend(trimmed.split_whitespace().map(|w| w.to_owned()));
+        }
+    }
+    if let Some(mut p) = paragraph.take() {
+        p.flush(width, &mut out);
+    }
+    let mut reflowed = out.join("\n");
+    if text.ends_with('\n') {
+        reflowed.push('\n');
+    }
+    reflowed
+}
+
+// Whether `target` can be the target of an intra-doc link, like `Foo`, `crate::foo()` or
+// `struct@Foo`. URLs, anchors and numbers, like in `[1]`, aren't.
+fn is_link_target(target: &str) -> bool {
+    !target.is_empty() &&
+    !target.starts_with(char::is_numeric) &&
+    !target.contains("://") &&
+    !target.starts_with('#') &&
+    target.chars().all(|c| c.is_alphanumeric() || "_:!()@<>".contains(c))
+}
+
+fn rewrite_line_links<F: Fn(&str) -> String>(line: &str, f: &F, out: &mut String) {
+    let mut rest = line;
+    while let Some(pos) = rest.find(&['`', '['][..]) {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if rest.starts_with('`') {
+            // Code spans are copied as is, up to the closing run of backticks.
+            let ticks = rest.find(|c| c != '`').unwrap_or(rest.len());
+            let end = rest[ticks..].find(&rest[..ticks])
+                                   .map_or(rest.len(), |end| ticks + end + ticks);
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        let close = match rest.find(']') {
+            Some(close) => close,
+            None => break,
+        };
+        let text = &rest[1..close];
+        let after = &rest[close + 1..];
+        if after.starts_with('(') {
+            if let Some(end) = after.find(')') {
+                let target = &after[1..end];
+                if is_link_target(target) {
+                    out.push_str(&format!("[{}]({})", text, f(target)));
+                } else {
+                    out.push_str(&rest[..close + 1 + end + 1]);
+                }
+                rest = &after[end + 1..];
+                continue;
+            }
+        } else if after.starts_with(':') && out.trim().is_empty() {
+            // A reference definition: `[label]: target`.
+            let target = after[1..].trim();
+            if is_link_target(target) {
+                out.push_str(&format!("[{}]: {}", text, f(target)));
+                rest = "";
+                continue;
+            }
+        } else if after.starts_with('[') {
+            // `[text][label]` links are rewritten through the definition of `label`.
+            let end = after.find(']').map_or(after.len(), |end| end + 1);
+            out.push_str(&rest[..close + 1 + end]);
+            rest = &after[end..];
+            continue;
+        } else {
+            let ticks = text.len() - text.trim_start_matches('`').len();
+            let inner = text.trim_matches('`');
+            if is_link_target(inner) && text.len() == inner.len() + 2 * ticks {
+                let ticks = &text[..ticks];
+                out.push_str(&format!("[{}{}{}]", ticks, f(inner), ticks));
+                rest = after;
+                continue;
+            }
+        }
+        out.push_str(&rest[..close + 1]);
+        rest = after;
+    }
+    out.push_str(rest);
+}
+
+// Applies `f` to the target of each intra-doc link of `text`: `[Foo]`, `` [`Foo`] ``,
+// `[text](crate::Foo)` and `[label]: crate::Foo`. Code spans, code blocks and links to URLs
+// are left untouched.
+pub fn rewrite_doc_links<F: Fn(&str) -> String>(text: &str, f: F) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_code = false;
+    for (pos, line) in text.split('\n').enumerate() {
+        if pos > 0 {
+            out.push('\n');
+        }
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            out.push_str(line);
+        } else if in_code {
+            out.push_str(line);
+        } else {
+            let mut rewritten = String::new();
+            rewrite_line_links(line, &f, &mut rewritten);
+            out.push_str(&rewritten);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Type;
+
+    #[test]
+    fn write_comment_unknown() {
+        let unknown = TypeStruct::new(Type::Unknown, "x");
+        assert_eq!(write_comment(&unknown, "c", false), "<!-- x -->\nc");
+        assert_eq!(write_comment(&unknown, "c", true), "<!-- x -->\nc");
+    }
+
+    #[test]
+    fn reflow_long_paragraph() {
+        let text = "aaaa ".repeat(30);
+        let reflowed = reflow_comment(&text, 80);
+        assert!(reflowed.lines().all(|l| l.len() <= 80));
+        assert_eq!(reflowed.lines().count(), 2);
+        assert_eq!(reflowed.split_whitespace().count(), 30);
+    }
+
+    #[test]
+    fn reflow_keeps_code_blocks() {
+        let text = "short\nline\n\n```\nlet x =   1;\nlet y = 2;\n```\n~~~\na   b\n~~~";
+        assert_eq!(reflow_comment(text, 80),
+                   "short line\n\n```\nlet x =   1;\nlet y = 2;\n```\n~~~\na   b\n~~~");
+        let text = "text\n\n    let x = 1;\n    let y = 2;";
+        assert_eq!(reflow_comment(text, 80), text);
+    }
+
+    #[test]
+    fn reflow_keeps_lists() {
+        assert_eq!(reflow_comment("para one\n  - item one\n  - item two", 80),
+                   "para one\n  - item one\n  - item two");
+        assert_eq!(reflow_comment("- a b c d\n  e\n1. f", 7), "- a b c\n  d e\n1. f");
+    }
+
+    #[test]
+    fn reflow_keeps_quotes_and_trailing_newline() {
+        assert_eq!(reflow_comment("a\nb\n> quoted   text\n>\n> more\nc", 80),
+                   "a b\n> quoted   text\n>\n> more\nc");
+        assert_eq!(reflow_comment("a\nb\n", 80), "a b\n");
+        assert_eq!(reflow_comment("a b\n", 80), "a b\n");
+        assert_eq!(reflow_comment("a b", 80), "a b");
+        assert_eq!(reflow_comment("a\n\n", 80), "a\n\n");
+    }
+
+    #[test]
+    fn rewrite_links() {
+        let f = |t: &str| format!("mycrate::{}", t.trim_start_matches("crate::"));
+        assert_eq!(rewrite_doc_links("see [`Foo`] and [Bar]", f),
+                   "see [`mycrate::Foo`] and [mycrate::Bar]");
+        assert_eq!(rewrite_doc_links("[text](crate::Foo)", f), "[text](mycrate::Foo)");
+        assert_eq!(rewrite_doc_links("[label]: crate::Foo", f), "[label]: mycrate::Foo");
+        let untouched = "plain Foo, `[Foo]`, [1] and [site](https://example.com)\n\
+                         ```\n[Foo]\n```";
+        assert_eq!(rewrite_doc_links(untouched, f), untouched);
+    }
+
+    #[test]
+    fn write_comment_debug_matches_display() {
+        let mut bound = TypeStruct::new(Type::Bound, "T");
+        bound.args = vec!["Clone".to_owned(), "Send".to_owned()];
+        let mut closure = TypeStruct::new(Type::Closure, "");
+        closure.args = vec!["x: u8".to_owned()];
+        let mut file = TypeStruct::new(Type::Mod, "m");
+        file.inline = false;
+        let mut max = TypeStruct::new(Type::Const, "MAX");
+        max.type_annotation = Some("u32".to_owned());
+        max.value = Some("1".to_owned());
+        let mut variant = TypeStruct::new(Type::Variant, "A");
+        variant.discriminant = Some("1".to_owned());
+        let rules = TypeStruct::new(Type::Macro, "m").with_raw_keyword("macro_rules!");
+        for t in &[bound, closure, file, max.clone(), variant, rules] {
+            assert_eq!(write_comment(t, "c", false), write_comment(t, "c", true));
+        }
+        assert_eq!(write_comment(&max, "c", true), "<!-- const MAX: u32 = 1 -->\nc");
+        // Only macro parents are written differently.
+        let mut f = TypeStruct::new(Type::Fn, "f");
+        f.parent = Some(Box::new(TypeStruct::new(Type::Macro, "m")));
+        assert_eq!(write_comment(&f, "c", true), "<!-- fn f -->\nc");
+        assert_eq!(write_comment(&f, "c", false), "<!-- macro m§fn f -->\nc");
+    }
+}